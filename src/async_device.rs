@@ -0,0 +1,511 @@
+//! # Async SPI protocol abstraction
+//!
+//! [AsyncFlash] offers the operations of the [blocking device](crate::device::Flash) based on
+//! [embedded-hal-async SpiDevice](embedded_hal_async::spi::SpiDevice). Requires the `async` feature.
+//!
+//! Instead of spinning on the status register, the driver sleeps between the status polls using the
+//! given [embedded-hal-async DelayNs](embedded_hal_async::delay::DelayNs) implementation. So the
+//! executor is free to run other tasks while erase or program operations are in progress.
+//! All operations return when the chip has completed the operation.
+//!
+//! ````
+//!# use mc_sst25::async_device::AsyncFlash;
+//!# use mc_sst25::example::{MockBus, MockDelay, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!# let delay = MockDelay::default();
+//!#
+//!# embassy_futures::block_on(async {
+//! let mut device = AsyncFlash::new(bus, pin_wp, pin_hold, delay);
+//!
+//! // Writing a single byte
+//! device.erase_full().await.unwrap();
+//! device.byte_program(0x0, 0x66).await.unwrap();
+//!
+//! // Writing larger data
+//! device.aai_program(0x1, &[0x1, 0x2, 0x3, 0x4]).await.unwrap();
+//!
+//! // Reading data starting at address 0x0
+//! let data = device.read::<5>(0x0).await.unwrap();
+//! assert_eq!([0x66, 0x1, 0x2, 0x3, 0x4], data);
+//!# });
+//! ````
+//!
+//! ## Probing
+//!
+//! Like the blocking device, the chip variant may be detected by its ID:
+//!
+//! ````
+//!# use mc_sst25::async_device::AsyncFlash;
+//!# use mc_sst25::chip::Variant;
+//!# use mc_sst25::example::{MockBus, MockDelay, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!# let delay = MockDelay::default();
+//!#
+//!# embassy_futures::block_on(async {
+//! let device = AsyncFlash::probe(bus, pin_wp, pin_hold, delay).await.unwrap();
+//! assert_eq!(Variant::SST25VF080B, device.variant());
+//!# });
+//! ````
+//!
+//! ## Timeouts
+//!
+//! Waits are not limited by default. Like the blocking device, they may be limited by
+//! [timeouts](crate::device::Timeouts), e.g. matching the detected variant:
+//!
+//! ````
+//!# use mc_sst25::async_device::AsyncFlash;
+//!# use mc_sst25::device::Timeouts;
+//!# use mc_sst25::example::{MockBus, MockDelay, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!# let delay = MockDelay::default();
+//!#
+//!# embassy_futures::block_on(async {
+//! let device = AsyncFlash::probe(bus, pin_wp, pin_hold, delay).await.unwrap();
+//! let timeouts = Timeouts::for_variant(device.variant());
+//! let mut device = device.with_timeouts(timeouts);
+//!
+//! // Returns CommandError::Timeout, if the chip is still busy after 25 ms
+//! device.erase_sector(0x0).await.unwrap();
+//!# });
+//! ````
+use crate::chip::{ChipId, DeviceId, Variant};
+use crate::device::{
+    aai_part, address_command, assert_aai_buffer, assert_valid_range, block_erase_frame, identify_legacy,
+    plan_erase, read_frame, CommandError, ControlPin, ReadMode, Status, Timeouts, WaitFor, CMD_AAI_PROGRAM,
+    CMD_BLOCK_ERASE_32K, CMD_BLOCK_ERASE_64K, CMD_BYTE_PROGRAM, CMD_CHIP_ERASE, CMD_ENABLE_WRITE_STATUS,
+    CMD_READ_ID, CMD_READ_JEDEC_ID, CMD_READ_STATUS, CMD_SECTOR_ERASE, CMD_WRITE_DISABLE, CMD_WRITE_ENABLE,
+    CMD_WRITE_STATUS,
+};
+use crate::erase::{ErasePlan, EraseStep};
+use core::fmt::Debug;
+use embedded_hal::spi::Operation;
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::spi::SpiDevice;
+
+/// Delay in microseconds between status polls while an erase operation is in progress
+pub const ERASE_POLL_INTERVAL_US: u32 = 1000;
+
+/// Delay in microseconds between status polls while a program or status write operation is in progress
+pub const PROGRAM_POLL_INTERVAL_US: u32 = 5;
+
+/// Async flash device
+pub struct AsyncFlash<B: SpiDevice<u8>, W: ControlPin, H: ControlPin, D: DelayNs> {
+    /// SPI bus
+    bus: B,
+
+    /// GPIO WP pin
+    pin_write_protection: W,
+
+    /// GPIO Hold pin
+    pin_hold: H,
+
+    /// Delay used between status polls
+    delay: D,
+
+    /// Is the device configured?
+    configured: bool,
+
+    /// Chip variant used for address and command validation
+    variant: Variant,
+
+    /// Command used for reading memory
+    read_mode: ReadMode,
+
+    /// Maximum durations of waits, None for waiting infinitely
+    timeouts: Option<Timeouts>,
+}
+
+impl<B: SpiDevice<u8>, W: ControlPin, H: ControlPin, D: DelayNs> AsyncFlash<B, W, H, D>
+where
+    W::Error: Debug,
+    H::Error: Debug,
+{
+    /// Creates a new device instance. SST25VF080B is assumed as chip variant, which may be changed
+    /// by [AsyncFlash::set_variant].
+    pub fn new(bus: B, pin_write_protection: W, pin_hold: H, delay: D) -> Self {
+        Self {
+            bus,
+            pin_write_protection,
+            pin_hold,
+            delay,
+            configured: false,
+            variant: Variant::SST25VF080B,
+            read_mode: ReadMode::Normal,
+            timeouts: None,
+        }
+    }
+
+    /// Limits all waits by the given timeouts. Waits exceeding the timeout of the respective
+    /// operation fail with [CommandError::Timeout]. The status is polled in fixed intervals
+    /// ([ERASE_POLL_INTERVAL_US], [PROGRAM_POLL_INTERVAL_US]), so [Timeouts::poll_interval_us] is ignored.
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = Some(timeouts);
+        self
+    }
+
+    /// Returns the timeouts of waits, None if waiting infinitely
+    pub fn timeouts(&self) -> Option<Timeouts> {
+        self.timeouts
+    }
+
+    /// Creates a new device instance and identifies the attached chip variant (s. [AsyncFlash::detect])
+    pub async fn probe(
+        bus: B,
+        pin_write_protection: W,
+        pin_hold: H,
+        delay: D,
+    ) -> Result<Self, CommandError<B, W, H>> {
+        let mut device = Self::new(bus, pin_write_protection, pin_hold, delay);
+        device.detect().await?;
+
+        Ok(device)
+    }
+
+    /// Restores a safe chip state and returns bus and pins as (bus, WP, HOLD).
+    /// Waits until the chip is idle, exits AAI mode and disables writing.
+    ///
+    /// In case of an error, the device is returned along with the error (s. [AsyncFlash::free]).
+    #[allow(clippy::type_complexity)]
+    pub async fn release(mut self) -> Result<(B, W, H), (Self, CommandError<B, W, H>)> {
+        match self.restore_safe_state().await {
+            Ok(()) => Ok(self.free()),
+            Err(error) => Err((self, error)),
+        }
+    }
+
+    /// Returns bus and pins as (bus, WP, HOLD) without accessing the chip
+    pub fn free(self) -> (B, W, H) {
+        (self.bus, self.pin_write_protection, self.pin_hold)
+    }
+
+    /// Sets the chip variant, which determines the valid address range and supported commands
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+
+    /// Returns the configured chip variant
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Sets the command used by all memory read operations
+    pub fn set_read_mode(&mut self, mode: ReadMode) {
+        self.read_mode = mode;
+    }
+
+    /// Returns the command used by all memory read operations
+    pub fn read_mode(&self) -> ReadMode {
+        self.read_mode
+    }
+
+    /// Identifies the attached chip by its JEDEC ID and configures the matching variant.
+    /// Falls back to the legacy Read-ID command for variants not supporting JEDEC ID.
+    pub async fn detect(&mut self) -> Result<Variant, CommandError<B, W, H>> {
+        let jedec_id = self.read_jedec_id().await?;
+
+        let variant = match jedec_id.variant() {
+            Some(variant) => variant,
+            None => identify_legacy(jedec_id, self.read_id().await?)?,
+        };
+
+        self.variant = variant;
+        Ok(variant)
+    }
+
+    /// Reads and returns the status registers
+    pub async fn read_status(&mut self) -> Result<Status, CommandError<B, W, H>> {
+        let mut buffer = [0x0];
+        self.command_response(&[CMD_READ_STATUS], &mut buffer).await?;
+
+        Ok(Status::from_register(buffer[0]))
+    }
+
+    /// Enables write operations
+    pub async fn write_enable(&mut self) -> Result<(), CommandError<B, W, H>> {
+        self.command(&[CMD_WRITE_ENABLE]).await
+    }
+
+    /// Disables write operations
+    pub async fn write_disable(&mut self) -> Result<(), CommandError<B, W, H>> {
+        self.command(&[CMD_WRITE_DISABLE]).await
+    }
+
+    /// Writes the given status to status registers.
+    /// The status register is write-enabled by EWSR or WREN instruction depending on the variant.
+    /// The written bits are verified by reading the status back afterward. Only the bits implemented
+    /// by the variant are compared (s. [Variant::status_mask]).
+    pub async fn write_status(&mut self, status: Status) -> Result<(), CommandError<B, W, H>> {
+        if self.variant.commands().enable_write_status {
+            self.command(&[CMD_ENABLE_WRITE_STATUS]).await?;
+        } else {
+            self.write_enable().await?;
+        }
+
+        self.command(&[CMD_WRITE_STATUS, status.to_registers()]).await?;
+        self.wait(WaitFor::Program).await?;
+
+        let mask = self.variant.status_mask();
+        if self.read_status().await?.to_registers() & mask != status.to_registers() & mask {
+            return Err(CommandError::StatusWriteFailed);
+        }
+
+        Ok(())
+    }
+
+    /// Clears all bits in the selected 4 KByte sector to FFH.
+    /// Returns when the operation is completed.
+    pub async fn erase_sector(&mut self, address: u32) -> Result<(), CommandError<B, W, H>> {
+        assert_valid_range(self.variant, address, 1)?;
+
+        let mut frame = [CMD_SECTOR_ERASE, 0x0, 0x0, 0x0];
+        address_command(address, &mut frame);
+
+        self.erase_command(&frame, WaitFor::SectorErase).await
+    }
+
+    /// Clears all bits in the selected 32 KByte block to FFH. Address needs to be block aligned.
+    /// Returns when the operation is completed.
+    pub async fn erase_block_32k(&mut self, address: u32) -> Result<(), CommandError<B, W, H>> {
+        let frame = block_erase_frame(self.variant, CMD_BLOCK_ERASE_32K, address)?;
+        self.erase_command(&frame, WaitFor::BlockErase).await
+    }
+
+    /// Clears all bits in the selected 64 KByte block to FFH. Address needs to be block aligned.
+    /// Returns when the operation is completed.
+    pub async fn erase_block_64k(&mut self, address: u32) -> Result<(), CommandError<B, W, H>> {
+        let frame = block_erase_frame(self.variant, CMD_BLOCK_ERASE_64K, address)?;
+        self.erase_command(&frame, WaitFor::BlockErase).await
+    }
+
+    /// Erases the full chip. Returns when the operation is completed.
+    pub async fn erase_full(&mut self) -> Result<(), CommandError<B, W, H>> {
+        self.erase_command(&[CMD_CHIP_ERASE], WaitFor::ChipErase).await
+    }
+
+    /// Plans the minimal sequence of erase operations covering exactly the range from start (inclusive)
+    /// to end (exclusive). Both bounds need to be sector aligned.
+    pub fn plan_erase(&self, start: u32, end: u32) -> Result<ErasePlan, CommandError<B, W, H>> {
+        plan_erase(self.variant, start, end)
+    }
+
+    /// Executes a single erase operation, e.g. a step of an [erase plan](AsyncFlash::plan_erase)
+    pub async fn erase(&mut self, step: EraseStep) -> Result<(), CommandError<B, W, H>> {
+        match step {
+            EraseStep::Full => self.erase_full().await,
+            EraseStep::Block64K(address) => self.erase_block_64k(address).await,
+            EraseStep::Block32K(address) => self.erase_block_32k(address).await,
+            EraseStep::Sector(address) => self.erase_sector(address).await,
+        }
+    }
+
+    /// Erases the range from start (inclusive) to end (exclusive) using the minimal sequence of
+    /// erase operations. Both bounds need to be sector aligned.
+    pub async fn erase_range(&mut self, start: u32, end: u32) -> Result<(), CommandError<B, W, H>> {
+        for step in self.plan_erase(start, end)? {
+            self.erase(step).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Programs/Writes the given byte at the given address. Disables internal write protection.
+    /// Returns when the operation is completed.
+    pub async fn byte_program(&mut self, address: u32, data: u8) -> Result<(), CommandError<B, W, H>> {
+        assert_valid_range(self.variant, address, 1)?;
+
+        self.write_enable().await?;
+        self.assert_not_busy().await?;
+
+        let mut frame = [CMD_BYTE_PROGRAM, 0x0, 0x0, 0x0, data];
+        address_command(address, &mut frame);
+
+        self.command(&frame).await?;
+        self.wait(WaitFor::Program).await
+    }
+
+    /// Auto address increment (AAI) programming for writing larger amount of data
+    /// Buffer needs to contain at least two bytes and an even data amount
+    pub async fn aai_program(&mut self, address: u32, buffer: &[u8]) -> Result<(), CommandError<B, W, H>> {
+        assert_aai_buffer(self.variant, address, buffer)?;
+
+        self.aai_words(address, buffer).await?;
+        self.write_disable().await
+    }
+
+    /// Writes data of any length starting at any address. Bytes outside the word aligned bulk are
+    /// written by Byte-Program, the bulk by AAI programming if supported by the variant.
+    /// Write operations are disabled afterward, also in case of an error.
+    pub async fn write(&mut self, address: u32, data: &[u8]) -> Result<(), CommandError<B, W, H>> {
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        assert_valid_range(self.variant, address, data.len())?;
+
+        let result = self.write_segments(address, data).await;
+        let disable_result = self.write_disable().await;
+
+        result.and(disable_result)
+    }
+
+    /// Reads data with length L starting at the given address
+    pub async fn read<const L: usize>(&mut self, address: u32) -> Result<[u8; L], CommandError<B, W, H>> {
+        let mut buffer = [0x0; L];
+        self.read_into(address, &mut buffer).await?;
+        Ok(buffer)
+    }
+
+    /// Reads data starting at the given address, filling the given buffer
+    pub async fn read_into(&mut self, address: u32, buffer: &mut [u8]) -> Result<(), CommandError<B, W, H>> {
+        let mut frame = [0x0; 5];
+        let frame = read_frame(self.variant, self.read_mode, address, buffer.len(), &mut frame)?;
+
+        self.command_response(frame, buffer).await
+    }
+
+    /// Reads the JEDEC ID (manufacturer, memory type and capacity)
+    pub async fn read_jedec_id(&mut self) -> Result<ChipId, CommandError<B, W, H>> {
+        let mut buffer = [0x0; 3];
+        self.command_response(&[CMD_READ_JEDEC_ID], &mut buffer).await?;
+
+        Ok(ChipId::from_bytes(buffer))
+    }
+
+    /// Reads manufacturer and device ID using the legacy Read-ID command.
+    /// Address 0x0 is used, so the manufacturer ID is returned first.
+    pub async fn read_id(&mut self) -> Result<DeviceId, CommandError<B, W, H>> {
+        let mut buffer = [0x0; 2];
+        self.command_response(&[CMD_READ_ID, 0x0, 0x0, 0x0], &mut buffer).await?;
+
+        Ok(DeviceId::from_bytes(buffer))
+    }
+
+    /// Writes the bytes outside of the AAI part by Byte-Program first, followed by the AAI part.
+    /// AAI mode is not exited.
+    async fn write_segments(&mut self, address: u32, data: &[u8]) -> Result<(), CommandError<B, W, H>> {
+        let aai = aai_part(self.variant, address, data.len());
+
+        for (index, byte) in data.iter().enumerate() {
+            if !aai.contains(&index) {
+                self.byte_program(address + index as u32, *byte).await?;
+            }
+        }
+
+        if aai.is_empty() {
+            return Ok(());
+        }
+
+        self.aai_words(address + aai.start as u32, &data[aai]).await
+    }
+
+    /// Enables writing and programs the given buffer word by word in AAI mode.
+    /// Waits for the completion of each word. AAI mode is not exited.
+    async fn aai_words(&mut self, address: u32, buffer: &[u8]) -> Result<(), CommandError<B, W, H>> {
+        self.write_enable().await?;
+        self.assert_not_busy().await?;
+
+        let mut frame = [CMD_AAI_PROGRAM, 0x0, 0x0, 0x0, buffer[0], buffer[1]];
+        address_command(address, &mut frame);
+        self.command(&frame).await?;
+        self.wait(WaitFor::Program).await?;
+
+        for chunk in buffer[2..].chunks(2) {
+            self.command(&[CMD_AAI_PROGRAM, chunk[0], chunk[1]]).await?;
+            self.wait(WaitFor::Program).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends the given erase command frame and waits until the operation is completed
+    async fn erase_command(&mut self, frame: &[u8], operation: WaitFor) -> Result<(), CommandError<B, W, H>> {
+        self.write_enable().await?;
+        self.assert_not_busy().await?;
+
+        self.command(frame).await?;
+        self.wait(operation).await
+    }
+
+    /// Sends the given command frame within a single transaction
+    async fn command(&mut self, frame: &[u8]) -> Result<(), CommandError<B, W, H>> {
+        self.configure()?;
+        self.bus
+            .transaction(&mut [Operation::Write(frame)])
+            .await
+            .map_err(CommandError::TransferError)
+    }
+
+    /// Sends the given command frame and reads the response within a single transaction
+    async fn command_response(
+        &mut self,
+        frame: &[u8],
+        response: &mut [u8],
+    ) -> Result<(), CommandError<B, W, H>> {
+        self.configure()?;
+        self.bus
+            .transaction(&mut [Operation::Write(frame), Operation::Read(response)])
+            .await
+            .map_err(CommandError::TransferError)
+    }
+
+    /// Returns an error in case device is busy
+    async fn assert_not_busy(&mut self) -> Result<(), CommandError<B, W, H>> {
+        if self.read_status().await?.busy {
+            return Err(CommandError::Busy);
+        }
+
+        Ok(())
+    }
+
+    /// Sleeps between status polls until device is not busy anymore. Returns a timeout error once
+    /// the timeout of the given operation expired, if timeouts are enabled.
+    async fn wait(&mut self, operation: WaitFor) -> Result<(), CommandError<B, W, H>> {
+        let interval_us = match operation {
+            WaitFor::Program => PROGRAM_POLL_INTERVAL_US,
+            _ => ERASE_POLL_INTERVAL_US,
+        };
+        let timeout = self.timeouts.map(|timeouts| operation.timeout(&timeouts));
+        let mut elapsed: u32 = 0;
+
+        while self.read_status().await?.busy {
+            if timeout.is_some_and(|timeout| elapsed >= timeout) {
+                return Err(CommandError::Timeout);
+            }
+
+            self.delay.delay_us(interval_us).await;
+            elapsed = elapsed.saturating_add(interval_us);
+        }
+
+        Ok(())
+    }
+
+    /// Waits until the chip is idle and disables writing, which also exits AAI mode
+    async fn restore_safe_state(&mut self) -> Result<(), CommandError<B, W, H>> {
+        self.wait(WaitFor::ChipErase).await?;
+        self.write_disable().await
+    }
+
+    /// Sets the base GPIO states once
+    fn configure(&mut self) -> Result<(), CommandError<B, W, H>> {
+        if self.configured {
+            return Ok(());
+        }
+
+        self.pin_hold.set_high().map_err(CommandError::HoldPinError)?;
+        self.pin_write_protection
+            .set_low()
+            .map_err(CommandError::WriteProtectionPinError)?;
+        self.configured = true;
+
+        Ok(())
+    }
+}
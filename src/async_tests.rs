@@ -0,0 +1,609 @@
+use crate::async_device::{AsyncFlash, ERASE_POLL_INTERVAL_US, PROGRAM_POLL_INTERVAL_US};
+use crate::chip::{ChipId, Variant};
+use crate::device::{CommandError, NoPin, ReadMode, Status, Timeouts};
+use crate::mocks::{BusError, MockAsyncDelay, MockAsyncSPIBus, MockPin, PinError};
+use embassy_futures::block_on;
+use embedded_hal::spi::Operation;
+use embedded_storage_async::nor_flash::{self, NorFlashError, NorFlashErrorKind};
+use mockall::Sequence;
+
+#[test]
+fn test_async_read_status_success() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_status_request(0x1C)
+        .into_flash();
+
+    let status = block_on(flash.read_status()).unwrap();
+
+    assert!(!status.busy);
+    assert!(status.block0_protected);
+    assert!(status.block1_protected);
+    assert!(status.block2_protected);
+}
+
+#[test]
+fn test_async_configure_hold_pin_error() {
+    let mut flash = MockedAsyncPeripherals::default().hold_error().into_flash();
+
+    let result = block_on(flash.read_status());
+    assert!(matches!(
+        result.unwrap_err(),
+        CommandError::HoldPinError(PinError::Error1)
+    ));
+}
+
+#[test]
+fn test_async_read_status_transfer_error() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .spi_transfer_error()
+        .into_flash();
+
+    let result = block_on(flash.read_status());
+    assert!(matches!(
+        result.unwrap_err(),
+        CommandError::TransferError(BusError::Error1)
+    ));
+}
+
+#[test]
+fn test_async_write_status_success() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0101_0000])
+        .expect_single_write(&[0b0000_0001, 0b0000_1000])
+        .expect_status_request(0x0)
+        .expect_status_request(0b0000_1000)
+        .into_flash();
+
+    let status = Status {
+        block1_protected: true,
+        ..Default::default()
+    };
+
+    block_on(flash.write_status(status)).unwrap();
+}
+
+#[test]
+fn test_async_write_status_verification_failed() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0101_0000])
+        .expect_single_write(&[0b0000_0001, 0b0000_1000])
+        .expect_status_request(0x0)
+        .expect_status_request(0x0)
+        .into_flash();
+
+    let status = Status {
+        block1_protected: true,
+        ..Default::default()
+    };
+
+    let result = block_on(flash.write_status(status));
+    assert!(matches!(result.unwrap_err(), CommandError::StatusWriteFailed));
+}
+
+#[test]
+fn test_async_erase_sector_sleeps_between_polls() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0000_0110])
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b0010_0000, 0x01, 0x20, 0x00])
+        .expect_status_request(0x1)
+        .expect_delay(ERASE_POLL_INTERVAL_US)
+        .expect_status_request(0x1)
+        .expect_delay(ERASE_POLL_INTERVAL_US)
+        .expect_status_request(0x0)
+        .into_flash();
+
+    block_on(flash.erase_sector(0x12000)).unwrap();
+}
+
+#[test]
+fn test_async_erase_sector_timeout() {
+    let timeouts = Timeouts {
+        sector_erase_us: 2 * ERASE_POLL_INTERVAL_US,
+        ..Timeouts::default()
+    };
+
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0000_0110])
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b0010_0000, 0x01, 0x20, 0x00])
+        .expect_status_request(0x1)
+        .expect_delay(ERASE_POLL_INTERVAL_US)
+        .expect_status_request(0x1)
+        .expect_delay(ERASE_POLL_INTERVAL_US)
+        .expect_status_request(0x1)
+        .into_flash()
+        .with_timeouts(timeouts);
+
+    let result = block_on(flash.erase_sector(0x12000));
+    assert!(matches!(result.unwrap_err(), CommandError::Timeout));
+}
+
+#[test]
+fn test_async_erase_sector_busy() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0000_0110])
+        .expect_status_request(0x1)
+        .into_flash();
+
+    let result = block_on(flash.erase_sector(0x12000));
+    assert!(matches!(result.unwrap_err(), CommandError::Busy));
+}
+
+#[test]
+fn test_async_erase_sector_invalid_address() {
+    let mut flash = MockedAsyncPeripherals::default().into_flash();
+
+    let result = block_on(flash.erase_sector(0x100000));
+    assert!(matches!(result.unwrap_err(), CommandError::InvalidAddress));
+}
+
+#[test]
+fn test_async_erase_block_64k_unaligned_address() {
+    let mut flash = MockedAsyncPeripherals::default().into_flash();
+
+    let result = block_on(flash.erase_block_64k(0x8000));
+    assert!(matches!(result.unwrap_err(), CommandError::UnalignedAddress));
+}
+
+#[test]
+fn test_async_erase_block_64k_unsupported() {
+    let mut flash = MockedAsyncPeripherals::default().into_flash();
+    flash.set_variant(Variant::SST25VF010A);
+
+    let result = block_on(flash.erase_block_64k(0x0));
+    assert!(matches!(result.unwrap_err(), CommandError::UnsupportedCommand));
+}
+
+#[test]
+fn test_async_erase_range_waits_for_each_step() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0000_0110])
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b0010_0000, 0x00, 0x70, 0x00])
+        .expect_status_request(0x1)
+        .expect_delay(ERASE_POLL_INTERVAL_US)
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b0000_0110])
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b0101_0010, 0x00, 0x80, 0x00])
+        .expect_status_request(0x0)
+        .into_flash();
+
+    block_on(flash.erase_range(0x7000, 0x10000)).unwrap();
+}
+
+#[test]
+fn test_async_erase_full_success() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0000_0110])
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b0110_0000])
+        .expect_status_request(0x1)
+        .expect_delay(ERASE_POLL_INTERVAL_US)
+        .expect_status_request(0x0)
+        .into_flash();
+
+    block_on(flash.erase_full()).unwrap();
+}
+
+#[test]
+fn test_async_byte_program_success() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0000_0110])
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b0000_0010, 0x01, 0x20, 0x03, 0x66])
+        .expect_status_request(0x1)
+        .expect_delay(PROGRAM_POLL_INTERVAL_US)
+        .expect_status_request(0x0)
+        .into_flash();
+
+    block_on(flash.byte_program(0x12003, 0x66)).unwrap();
+}
+
+#[test]
+fn test_async_byte_program_timeout() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0000_0110])
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b0000_0010, 0x01, 0x20, 0x03, 0x66])
+        .expect_status_request(0x1)
+        .expect_delay(PROGRAM_POLL_INTERVAL_US)
+        .expect_status_request(0x1)
+        .expect_delay(PROGRAM_POLL_INTERVAL_US)
+        .expect_status_request(0x1)
+        .into_flash()
+        .with_timeouts(Timeouts::default());
+
+    let result = block_on(flash.byte_program(0x12003, 0x66));
+    assert!(matches!(result.unwrap_err(), CommandError::Timeout));
+}
+
+#[test]
+fn test_async_aai_program_success() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0000_0110])
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b1010_1101, 0x01, 0x20, 0x00, 0x1, 0x2])
+        .expect_status_request(0x1)
+        .expect_delay(PROGRAM_POLL_INTERVAL_US)
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b1010_1101, 0x3, 0x4])
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b0000_0100])
+        .into_flash();
+
+    block_on(flash.aai_program(0x12000, &[0x1, 0x2, 0x3, 0x4])).unwrap();
+}
+
+#[test]
+fn test_async_aai_program_buffer_uneven() {
+    let mut flash = MockedAsyncPeripherals::default().into_flash();
+
+    let result = block_on(flash.aai_program(0x0, &[0x1, 0x2, 0x3]));
+    assert!(matches!(result.unwrap_err(), CommandError::BufferUneven));
+}
+
+#[test]
+fn test_async_read_success() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b0000_0011, 0x01, 0x20, 0x03], &[0x1, 0x2, 0x3])
+        .into_flash();
+
+    assert_eq!([0x1, 0x2, 0x3], block_on(flash.read::<3>(0x12003)).unwrap());
+}
+
+#[test]
+fn test_async_read_into_high_speed() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b0000_1011, 0x01, 0x20, 0x03, 0x0], &[0x1, 0x2])
+        .into_flash();
+    flash.set_read_mode(ReadMode::HighSpeed);
+
+    let mut buffer = [0x0; 2];
+    block_on(flash.read_into(0x12003, &mut buffer)).unwrap();
+    assert_eq!([0x1, 0x2], buffer);
+}
+
+#[test]
+fn test_async_read_into_invalid_range() {
+    let mut flash = MockedAsyncPeripherals::default().into_flash();
+
+    let mut buffer = [0x0; 2];
+    let result = block_on(flash.read_into(0xfffff, &mut buffer));
+    assert!(matches!(result.unwrap_err(), CommandError::InvalidAddress));
+}
+
+#[test]
+fn test_async_detect_jedec_id() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b1001_1111], &[0xbf, 0x25, 0x41])
+        .into_flash();
+
+    assert_eq!(Variant::SST25VF016B, block_on(flash.detect()).unwrap());
+    assert_eq!(Variant::SST25VF016B, flash.variant());
+}
+
+#[test]
+fn test_async_detect_legacy_id() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b1001_1111], &[0xff, 0xff, 0xff])
+        .expect_command_response(&[0b1001_0000, 0x0, 0x0, 0x0], &[0xbf, 0x49])
+        .into_flash();
+
+    assert_eq!(Variant::SST25VF010A, block_on(flash.detect()).unwrap());
+}
+
+#[test]
+fn test_async_detect_unknown_chip() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b1001_1111], &[0x1, 0x2, 0x3])
+        .expect_command_response(&[0b1001_0000, 0x0, 0x0, 0x0], &[0x1, 0x2])
+        .into_flash();
+
+    let expected = ChipId {
+        manufacturer: 0x1,
+        memory_type: 0x2,
+        capacity: 0x3,
+    };
+    let error = block_on(flash.detect()).unwrap_err();
+    assert!(matches!(error, CommandError::UnknownChip(id) if id == expected));
+}
+
+#[test]
+fn test_async_nor_flash_read() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x01], &[0x1, 0x2, 0x3])
+        .into_flash();
+
+    let mut buffer = [0x0; 3];
+    block_on(nor_flash::ReadNorFlash::read(&mut flash, 0x1001, &mut buffer)).unwrap();
+    assert_eq!([0x1, 0x2, 0x3], buffer);
+    assert_eq!(1048576, nor_flash::ReadNorFlash::capacity(&flash));
+}
+
+#[test]
+fn test_async_nor_flash_erase_sleeps_between_polls() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0000_0110])
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b0010_0000, 0x0, 0x10, 0x0])
+        .expect_status_request(0x1)
+        .expect_delay(ERASE_POLL_INTERVAL_US)
+        .expect_status_request(0x0)
+        .into_flash();
+
+    block_on(nor_flash::NorFlash::erase(&mut flash, 0x1000, 0x2000)).unwrap();
+}
+
+#[test]
+fn test_async_nor_flash_erase_not_aligned() {
+    let mut flash = MockedAsyncPeripherals::default().into_flash();
+
+    let error = block_on(nor_flash::NorFlash::erase(&mut flash, 0x1000, 0x1800)).unwrap_err();
+    assert_eq!(NorFlashErrorKind::NotAligned, error.kind());
+}
+
+#[test]
+fn test_async_nor_flash_write_odd_address() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0000_0110])
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x01, 0x1])
+        .expect_status_request(0x1)
+        .expect_delay(PROGRAM_POLL_INTERVAL_US)
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b0000_0110])
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x04, 0x4])
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b0000_0110])
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b1010_1101, 0x0, 0x10, 0x02, 0x2, 0x3])
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b0000_0100])
+        .into_flash();
+
+    block_on(nor_flash::NorFlash::write(
+        &mut flash,
+        0x1001,
+        &[0x1, 0x2, 0x3, 0x4],
+    ))
+    .unwrap();
+}
+
+#[test]
+fn test_async_write_disables_on_error() {
+    let mut flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0000_0110])
+        .expect_status_request(0x0)
+        .spi_transfer_error()
+        .expect_single_write(&[0b0000_0100])
+        .into_flash();
+
+    let result = block_on(flash.write(0x1000, &[0x1, 0x2]));
+    assert!(matches!(
+        result.unwrap_err(),
+        CommandError::TransferError(BusError::Error1)
+    ));
+}
+
+#[test]
+fn test_async_nor_flash_write_out_of_bounds() {
+    let mut flash = MockedAsyncPeripherals::default().into_flash();
+
+    let error = block_on(nor_flash::NorFlash::write(&mut flash, 0xfffff, &[0x1, 0x2])).unwrap_err();
+    assert_eq!(NorFlashErrorKind::OutOfBounds, error.kind());
+}
+
+#[test]
+fn test_async_no_pins() {
+    let peripherals = MockedAsyncPeripherals::default().expect_status_request(0x1C);
+    let mut flash = AsyncFlash::new(
+        peripherals.bus,
+        NoPin::default(),
+        NoPin::default(),
+        peripherals.delay,
+    );
+
+    let status = block_on(flash.read_status()).unwrap();
+    assert!(status.block0_protected);
+}
+
+#[test]
+fn test_async_release_success() {
+    let flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_status_request(0x1)
+        .expect_delay(ERASE_POLL_INTERVAL_US)
+        .expect_status_request(0x0)
+        .expect_single_write(&[0b0000_0100])
+        .into_flash();
+
+    assert!(block_on(flash.release()).is_ok());
+}
+
+#[test]
+fn test_async_release_timeout() {
+    let timeouts = Timeouts {
+        chip_erase_us: ERASE_POLL_INTERVAL_US,
+        ..Timeouts::default()
+    };
+
+    let flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .expect_status_request(0x1)
+        .expect_delay(ERASE_POLL_INTERVAL_US)
+        .expect_status_request(0x1)
+        .into_flash()
+        .with_timeouts(timeouts);
+
+    let (_flash, error) = block_on(flash.release()).err().unwrap();
+    assert!(matches!(error, CommandError::Timeout));
+}
+
+#[test]
+fn test_async_release_transfer_error() {
+    let flash = MockedAsyncPeripherals::default()
+        .mock_configure()
+        .spi_transfer_error()
+        .into_flash();
+
+    let (flash, error) = block_on(flash.release()).err().unwrap();
+    assert!(matches!(error, CommandError::TransferError(BusError::Error1)));
+
+    let (_bus, _pin_wp, _pin_hold) = flash.free();
+}
+
+#[derive(Default)]
+struct MockedAsyncPeripherals {
+    pub pin_hold: MockPin,
+    pub pin_wp: MockPin,
+    pub bus: MockAsyncSPIBus,
+    pub delay: MockAsyncDelay,
+    pub sequence: Sequence,
+}
+
+impl MockedAsyncPeripherals {
+    /// Returns a new async flash device with mocked peripherals
+    pub fn into_flash(self) -> AsyncFlash<MockAsyncSPIBus, MockPin, MockPin, MockAsyncDelay> {
+        AsyncFlash::new(self.bus, self.pin_wp, self.pin_hold, self.delay)
+    }
+
+    /// Simulates a error when setting HOLD state
+    pub fn hold_error(mut self) -> Self {
+        self.pin_hold.expect_set_high().times(1).return_const(Err(PinError::Error1));
+        self
+    }
+
+    /// Simulates a SPI transfer error
+    pub fn spi_transfer_error(mut self) -> Self {
+        self.bus
+            .expect_transaction()
+            .times(1)
+            .in_sequence(&mut self.sequence)
+            .return_const(Err(BusError::Error1));
+        self
+    }
+
+    /// Mocks the one-time configuration logic
+    pub fn mock_configure(mut self) -> Self {
+        self.pin_hold.expect_set_high().times(1).return_const(Ok(()));
+        self.pin_wp.expect_set_low().times(1).return_const(Ok(()));
+
+        self
+    }
+
+    /// Expects a sleep of the given duration between status polls
+    pub fn expect_delay(mut self, us: u32) -> Self {
+        self.delay
+            .expect_delay_us()
+            .withf(move |value| *value == us)
+            .times(1)
+            .in_sequence(&mut self.sequence)
+            .return_const(());
+        self
+    }
+
+    /// Expects a status command request and returns the given status register
+    pub fn expect_status_request(mut self, status: u8) -> Self {
+        self.bus
+            .expect_transaction()
+            .times(1)
+            .in_sequence(&mut self.sequence)
+            .returning(move |operations| {
+                assert_eq!(2, operations.len());
+
+                match &operations[0] {
+                    Operation::Write(cmd) => {
+                        assert_eq!(&[0b0000_0101], cmd);
+                    }
+                    _ => panic!("Expected first operation to be Write"),
+                }
+
+                match &mut operations[1] {
+                    Operation::Read(buffer) => {
+                        assert_eq!(1, buffer.len());
+                        buffer[0] = status;
+                    }
+                    _ => panic!("Expected second operation to be Read"),
+                }
+
+                Ok(())
+            });
+
+        self
+    }
+
+    /// Expects a single write operation
+    pub fn expect_single_write(mut self, command: &'static [u8]) -> Self {
+        self.bus
+            .expect_transaction()
+            .times(1)
+            .in_sequence(&mut self.sequence)
+            .returning(move |operations| {
+                assert_eq!(1, operations.len());
+                match &operations[0] {
+                    Operation::Write(data) => {
+                        assert_eq!(&command, data);
+                    }
+                    _ => panic!("Expected Write operation"),
+                }
+
+                Ok(())
+            });
+
+        self
+    }
+
+    /// Expects a command followed by reading the given response within one transaction
+    pub fn expect_command_response(mut self, command: &'static [u8], response: &'static [u8]) -> Self {
+        self.bus
+            .expect_transaction()
+            .times(1)
+            .in_sequence(&mut self.sequence)
+            .returning(move |operations| {
+                assert_eq!(2, operations.len(), "Operations: {operations:?}");
+
+                match &operations[0] {
+                    Operation::Write(data) => {
+                        assert_eq!(&command, data);
+                    }
+                    _ => panic!("Expected first operation to be Write"),
+                }
+
+                match &mut operations[1] {
+                    Operation::Read(buffer) => {
+                        assert_eq!(response.len(), buffer.len(), "Unexpected read length");
+                        buffer.copy_from_slice(response);
+                    }
+                    _ => panic!("Expected second operation to be Read"),
+                }
+
+                Ok(())
+            });
+
+        self
+    }
+}
@@ -0,0 +1,124 @@
+//! # Driver-owned SPI bus
+//!
+//! [OwnedBus] combines an exclusively owned [embedded-hal SpiBus](embedded_hal::spi::SpiBus) with
+//! the CE# (chip select) output and the MISO input. It implements [SpiDevice] by asserting CE#
+//! around each transaction, and [BusyLine] by asserting CE# while sampling MISO, which is required
+//! for [hardware end-of-write detection](crate::device#hardware-end-of-write-detection).
+//!
+//! ````
+//!# use mc_sst25::bus::OwnedBus;
+//!# use mc_sst25::device::{Flash, Memory};
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let spi = MockBus::default();
+//!# let pin_cs = MockPin::default();
+//!# let pin_miso = MockPin::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//! let bus = OwnedBus::new(spi, pin_cs, pin_miso);
+//! let mut device = Flash::new(bus, pin_wp, pin_hold);
+//!
+//! device.aai_program_with_busy_line(0x0, &[0x1, 0x2, 0x3, 0x4]).unwrap();
+//! ````
+//!
+//! *Note: As the bus is owned exclusively, it can not be shared with other devices.*
+use crate::device::BusyLine;
+use core::fmt::Debug;
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal::spi;
+use embedded_hal::spi::{ErrorKind, ErrorType, Operation, SpiBus, SpiDevice};
+
+/// SPI bus owned by the driver together with CE# and MISO pins
+pub struct OwnedBus<S: SpiBus<u8>, CS: OutputPin, M: InputPin> {
+    /// SPI bus
+    bus: S,
+
+    /// GPIO CE# pin
+    pin_cs: CS,
+
+    /// GPIO input connected to MISO (SO of the chip)
+    pin_miso: M,
+}
+
+/// Error of the [OwnedBus]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OwnedBusError<S, CS, M> {
+    /// Error of the SPI bus
+    Bus(S),
+
+    /// Error while setting GPIO state of CE# pin
+    ChipSelect(CS),
+
+    /// Error while reading GPIO state of MISO pin
+    Miso(M),
+}
+
+impl<S: spi::Error, CS: Debug, M: Debug> spi::Error for OwnedBusError<S, CS, M> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            OwnedBusError::Bus(error) => error.kind(),
+            OwnedBusError::ChipSelect(_) => ErrorKind::ChipSelectFault,
+            OwnedBusError::Miso(_) => ErrorKind::Other,
+        }
+    }
+}
+
+impl<S: SpiBus<u8>, CS: OutputPin, M: InputPin> OwnedBus<S, CS, M> {
+    /// Creates a new bus. CE# is not touched until the first transaction.
+    pub fn new(bus: S, pin_cs: CS, pin_miso: M) -> Self {
+        Self {
+            bus,
+            pin_cs,
+            pin_miso,
+        }
+    }
+
+    /// Returns bus and pins as (bus, CE#, MISO)
+    pub fn free(self) -> (S, CS, M) {
+        (self.bus, self.pin_cs, self.pin_miso)
+    }
+}
+
+impl<S: SpiBus<u8>, CS: OutputPin, M: InputPin> ErrorType for OwnedBus<S, CS, M> {
+    type Error = OwnedBusError<S::Error, CS::Error, M::Error>;
+}
+
+impl<S: SpiBus<u8>, CS: OutputPin, M: InputPin> SpiDevice<u8> for OwnedBus<S, CS, M> {
+    /// Executes the operations while CE# is asserted. CE# is released afterward, also in case of an error.
+    ///
+    /// *Note: [Operation::DelayNs] is not supported, as the driver never issues delays within a
+    /// transaction, and panics.*
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        self.pin_cs.set_low().map_err(OwnedBusError::ChipSelect)?;
+
+        let result = operations.iter_mut().try_for_each(|operation| match operation {
+            Operation::Read(buffer) => self.bus.read(buffer),
+            Operation::Write(buffer) => self.bus.write(buffer),
+            Operation::Transfer(read, write) => self.bus.transfer(read, write),
+            Operation::TransferInPlace(buffer) => self.bus.transfer_in_place(buffer),
+            Operation::DelayNs(_) => unimplemented!(),
+        });
+
+        let flush_result = self.bus.flush();
+        let cs_result = self.pin_cs.set_high();
+
+        result.map_err(OwnedBusError::Bus)?;
+        flush_result.map_err(OwnedBusError::Bus)?;
+        cs_result.map_err(OwnedBusError::ChipSelect)
+    }
+}
+
+impl<S: SpiBus<u8>, CS: OutputPin, M: InputPin> BusyLine for OwnedBus<S, CS, M> {
+    fn is_busy(&mut self) -> Result<bool, Self::Error> {
+        self.pin_cs.set_low().map_err(OwnedBusError::ChipSelect)?;
+
+        let result = self.pin_miso.is_low();
+        let cs_result = self.pin_cs.set_high();
+
+        let busy = result.map_err(OwnedBusError::Miso)?;
+        cs_result.map_err(OwnedBusError::ChipSelect)?;
+
+        Ok(busy)
+    }
+}
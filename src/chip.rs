@@ -0,0 +1,395 @@
+//! # Chip identification
+//!
+//! The chip can be identified either by the JEDEC ID (manufacturer, memory type and capacity) or by the
+//! legacy Read-ID command (manufacturer and device ID). Both IDs can be mapped to a known SST25 [Variant].
+//!
+//! ````
+//!# use mc_sst25::chip::Variant;
+//!# use mc_sst25::device::{Flash, Memory};
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//!# let mut device = Flash::new(bus, pin_wp, pin_hold);
+//!#
+//! let id = device.read_jedec_id().unwrap();
+//! assert_eq!(0xbf, id.manufacturer);
+//! assert_eq!(Some(Variant::SST25VF080B), id.variant());
+//!
+//! let id = device.read_id().unwrap();
+//! assert_eq!(0x8e, id.device);
+//! assert_eq!(Some(Variant::SST25VF080B), id.variant());
+//! ````
+//!
+//! # Catalog
+//!
+//! Each [Variant] carries its memory geometry and the set of optional [Commands] it supports. The
+//! variant of a [device](crate::device::Flash) is used for validating addresses and commands.
+//!
+//! ````
+//!# use mc_sst25::chip::{Variant, SECTOR_SIZE};
+//! let variant = Variant::SST25VF080B;
+//!
+//! assert_eq!(1048576, variant.capacity());
+//! assert_eq!(SECTOR_SIZE, variant.sector_size());
+//! assert!(variant.commands().block_erase_64k);
+//! ````
+//!
+//! The block-protection bits (BP3..BP0) of the status register protect an upper fraction of the
+//! memory. The fractions depend on the variant:
+//!
+//! ````
+//!# use mc_sst25::chip::Variant;
+//! let variant = Variant::SST25VF080B;
+//!
+//! assert_eq!(0xf0000..0x100000, variant.protected_range(0b0001));
+//! assert_eq!(0x80000..0x100000, variant.protected_range(0b0100));
+//! assert_eq!(0x0..0x100000, variant.protected_range(0b0101));
+//! assert_eq!(Some(0b0010), variant.protection_bits(0xe0000..0x100000));
+//! assert_eq!(None, variant.protection_bits(0x0..0x10000));
+//! ````
+use core::ops::Range;
+
+/// Manufacturer ID of Microchip (formerly SST)
+pub const MANUFACTURER_SST: u8 = 0xbf;
+
+/// Manufacturer ID of parts produced by ON Semiconductor (formerly Sanyo) for Microchip
+pub const MANUFACTURER_ON_SEMI: u8 = 0x62;
+
+/// Size of a sector (smallest erasable unit) in bytes
+pub const SECTOR_SIZE: u32 = 4096;
+
+/// Size of a 32 KByte block in bytes
+pub const BLOCK_32K_SIZE: u32 = 32768;
+
+/// Size of a 64 KByte block in bytes
+pub const BLOCK_64K_SIZE: u32 = 65536;
+
+/// Known SST25 flash memory variants
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Variant {
+    /// 1 Mbit (128 KByte)
+    SST25VF010A,
+
+    /// 2 Mbit (256 KByte)
+    SST25VF020B,
+
+    /// 4 Mbit (512 KByte)
+    SST25VF040B,
+
+    /// 8 Mbit (1 MByte)
+    SST25VF080B,
+
+    /// 16 Mbit (2 MByte)
+    SST25VF016B,
+
+    /// 32 Mbit (4 MByte)
+    SST25VF032B,
+
+    /// 64 Mbit (8 MByte)
+    SST25VF064C,
+
+    /// 4 Mbit (512 KByte)
+    SST25PF040C,
+
+    /// 512 Kbit (64 KByte)
+    SST25WF512,
+
+    /// 1 Mbit (128 KByte)
+    SST25WF010,
+
+    /// 2 Mbit (256 KByte)
+    SST25WF020,
+
+    /// 4 Mbit (512 KByte)
+    SST25WF040,
+
+    /// 8 Mbit (1 MByte)
+    SST25WF080,
+}
+
+impl Variant {
+    /// All known variants
+    pub const ALL: [Variant; 13] = [
+        Variant::SST25VF010A,
+        Variant::SST25VF020B,
+        Variant::SST25VF040B,
+        Variant::SST25VF080B,
+        Variant::SST25VF016B,
+        Variant::SST25VF032B,
+        Variant::SST25VF064C,
+        Variant::SST25PF040C,
+        Variant::SST25WF512,
+        Variant::SST25WF010,
+        Variant::SST25WF020,
+        Variant::SST25WF040,
+        Variant::SST25WF080,
+    ];
+
+    /// Returns the memory capacity in bytes
+    pub const fn capacity(&self) -> u32 {
+        match self {
+            Variant::SST25WF512 => 65536,
+            Variant::SST25VF010A | Variant::SST25WF010 => 131072,
+            Variant::SST25VF020B | Variant::SST25WF020 => 262144,
+            Variant::SST25VF040B | Variant::SST25PF040C | Variant::SST25WF040 => 524288,
+            Variant::SST25VF080B | Variant::SST25WF080 => 1048576,
+            Variant::SST25VF016B => 2097152,
+            Variant::SST25VF032B => 4194304,
+            Variant::SST25VF064C => 8388608,
+        }
+    }
+
+    /// Returns the size of a sector in bytes
+    pub const fn sector_size(&self) -> u32 {
+        SECTOR_SIZE
+    }
+
+    /// Returns the number of block-protection levels, which protect only a fraction of the memory.
+    /// Level one protects the upper 1/2^levels of the memory, each further level doubles the
+    /// protected size up to the upper half. Higher BP values protect the full memory.
+    pub const fn protection_levels(&self) -> u8 {
+        match self {
+            Variant::SST25VF010A
+            | Variant::SST25VF020B
+            | Variant::SST25WF512
+            | Variant::SST25WF010
+            | Variant::SST25WF020 => 2,
+            Variant::SST25VF040B | Variant::SST25PF040C | Variant::SST25WF040 => 3,
+            Variant::SST25VF080B | Variant::SST25WF080 => 4,
+            Variant::SST25VF016B => 5,
+            Variant::SST25VF032B => 6,
+            Variant::SST25VF064C => 7,
+        }
+    }
+
+    /// Returns the address range protected by the given block-protection bits (BP3..BP0).
+    /// The range is empty if no BP bit is set.
+    pub const fn protected_range(&self, bits: u8) -> Range<u32> {
+        let capacity = self.capacity();
+        let levels = self.protection_levels();
+
+        if bits == 0 {
+            return capacity..capacity;
+        }
+
+        if bits > levels {
+            return 0..capacity;
+        }
+
+        (capacity - (capacity >> (levels + 1 - bits)))..capacity
+    }
+
+    /// Returns the lowest block-protection bits (BP3..BP0) protecting exactly the given range.
+    /// An empty range maps to no protection. None if the range can not be expressed by the variant.
+    pub fn protection_bits(&self, range: Range<u32>) -> Option<u8> {
+        if range.is_empty() {
+            return Some(0);
+        }
+
+        (1..=self.protection_levels() + 1).find(|bits| self.protected_range(*bits) == range)
+    }
+
+    /// Returns the mask of the writable status register bits implemented by the variant, i.e. BPL
+    /// and the block-protection bits. Variants with less than four protection levels implement
+    /// only as many BP bits as levels, the remaining ones are reserved and read back as zero.
+    pub const fn status_mask(&self) -> u8 {
+        let levels = self.protection_levels();
+        let bp_bits = if levels < 4 { levels } else { 4 };
+
+        (1 << 7) | (((1 << bp_bits) - 1) << 2)
+    }
+
+    /// Returns the optional commands supported by the variant
+    pub const fn commands(&self) -> Commands {
+        match self {
+            Variant::SST25VF010A => Commands {
+                jedec_id: false,
+                block_erase_32k: true,
+                block_erase_64k: false,
+                aai_word_program: false,
+                high_speed_read: false,
+                enable_write_status: true,
+                end_of_write_detection: false,
+            },
+            Variant::SST25VF064C => Commands {
+                aai_word_program: false,
+                end_of_write_detection: false,
+                ..Commands::ALL
+            },
+            Variant::SST25PF040C => Commands {
+                aai_word_program: false,
+                enable_write_status: false,
+                end_of_write_detection: false,
+                ..Commands::ALL
+            },
+            Variant::SST25VF020B
+            | Variant::SST25VF040B
+            | Variant::SST25VF080B
+            | Variant::SST25VF016B
+            | Variant::SST25VF032B
+            | Variant::SST25WF512
+            | Variant::SST25WF010
+            | Variant::SST25WF020
+            | Variant::SST25WF040
+            | Variant::SST25WF080 => Commands::ALL,
+        }
+    }
+
+    /// Returns the JEDEC ID of the variant. None if the variant does not support the JEDEC ID command
+    pub const fn jedec_id(&self) -> Option<ChipId> {
+        let (manufacturer, memory_type, capacity) = match self {
+            Variant::SST25VF010A => return None,
+            Variant::SST25VF020B => (MANUFACTURER_SST, 0x25, 0x8c),
+            Variant::SST25VF040B => (MANUFACTURER_SST, 0x25, 0x8d),
+            Variant::SST25VF080B => (MANUFACTURER_SST, 0x25, 0x8e),
+            Variant::SST25VF016B => (MANUFACTURER_SST, 0x25, 0x41),
+            Variant::SST25VF032B => (MANUFACTURER_SST, 0x25, 0x4a),
+            Variant::SST25VF064C => (MANUFACTURER_SST, 0x25, 0x4b),
+            Variant::SST25PF040C => (MANUFACTURER_ON_SEMI, 0x06, 0x13),
+            Variant::SST25WF512 => (MANUFACTURER_SST, 0x25, 0x01),
+            Variant::SST25WF010 => (MANUFACTURER_SST, 0x25, 0x02),
+            Variant::SST25WF020 => (MANUFACTURER_SST, 0x25, 0x03),
+            Variant::SST25WF040 => (MANUFACTURER_SST, 0x25, 0x04),
+            Variant::SST25WF080 => (MANUFACTURER_SST, 0x25, 0x05),
+        };
+
+        Some(ChipId {
+            manufacturer,
+            memory_type,
+            capacity,
+        })
+    }
+
+    /// Returns the ID reported by the legacy Read-ID command
+    pub const fn device_id(&self) -> DeviceId {
+        let (manufacturer, device) = match self {
+            Variant::SST25VF010A => (MANUFACTURER_SST, 0x49),
+            Variant::SST25VF020B => (MANUFACTURER_SST, 0x8c),
+            Variant::SST25VF040B => (MANUFACTURER_SST, 0x8d),
+            Variant::SST25VF080B => (MANUFACTURER_SST, 0x8e),
+            Variant::SST25VF016B => (MANUFACTURER_SST, 0x41),
+            Variant::SST25VF032B => (MANUFACTURER_SST, 0x4a),
+            Variant::SST25VF064C => (MANUFACTURER_SST, 0x4b),
+            Variant::SST25PF040C => (MANUFACTURER_ON_SEMI, 0x12),
+            Variant::SST25WF512 => (MANUFACTURER_SST, 0x01),
+            Variant::SST25WF010 => (MANUFACTURER_SST, 0x02),
+            Variant::SST25WF020 => (MANUFACTURER_SST, 0x03),
+            Variant::SST25WF040 => (MANUFACTURER_SST, 0x04),
+            Variant::SST25WF080 => (MANUFACTURER_SST, 0x05),
+        };
+
+        DeviceId { manufacturer, device }
+    }
+}
+
+/// Optional commands supported by a variant
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Commands {
+    /// Read-JEDEC-ID (0x9F)
+    pub jedec_id: bool,
+
+    /// 32 KByte block erase (0x52)
+    pub block_erase_32k: bool,
+
+    /// 64 KByte block erase (0xD8)
+    pub block_erase_64k: bool,
+
+    /// Auto-address-increment word programming (0xAD)
+    pub aai_word_program: bool,
+
+    /// High-Speed-Read (0x0B)
+    pub high_speed_read: bool,
+
+    /// Enable-Write-Status-Register (0x50)
+    /// If not supported, the status register is write-enabled by Write-Enable (0x06).
+    pub enable_write_status: bool,
+
+    /// Hardware end-of-write detection during AAI programming (0x70/0x80)
+    pub end_of_write_detection: bool,
+}
+
+impl Commands {
+    /// All optional commands are supported
+    const ALL: Commands = Commands {
+        jedec_id: true,
+        block_erase_32k: true,
+        block_erase_64k: true,
+        aai_word_program: true,
+        high_speed_read: true,
+        enable_write_status: true,
+        end_of_write_detection: true,
+    };
+}
+
+/// ID returned by the JEDEC Read-ID command
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChipId {
+    /// Manufacturer ID, e.g. 0xBF for Microchip
+    pub manufacturer: u8,
+
+    /// Memory type, e.g. 0x25 for the SST25 series
+    pub memory_type: u8,
+
+    /// Memory capacity/device code, e.g. 0x8E for SST25VF080B
+    pub capacity: u8,
+}
+
+impl ChipId {
+    /// Maps the raw ID bytes to object
+    pub(crate) fn from_bytes(data: [u8; 3]) -> Self {
+        Self {
+            manufacturer: data[0],
+            memory_type: data[1],
+            capacity: data[2],
+        }
+    }
+
+    /// Returns the matching known variant, None if the ID is unknown
+    pub fn variant(&self) -> Option<Variant> {
+        Variant::ALL.into_iter().find(|variant| variant.jedec_id() == Some(*self))
+    }
+
+    /// True if all bytes are 0x00 or 0xFF, which indicates that no chip responded
+    pub fn is_blank(&self) -> bool {
+        is_blank(&[self.manufacturer, self.memory_type, self.capacity])
+    }
+}
+
+/// ID returned by the legacy Read-ID command
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeviceId {
+    /// Manufacturer ID, e.g. 0xBF for Microchip
+    pub manufacturer: u8,
+
+    /// Device ID, e.g. 0x8E for SST25VF080B
+    pub device: u8,
+}
+
+impl DeviceId {
+    /// Maps the raw ID bytes to object
+    pub(crate) fn from_bytes(data: [u8; 2]) -> Self {
+        Self {
+            manufacturer: data[0],
+            device: data[1],
+        }
+    }
+
+    /// Returns the matching known variant, None if the ID is unknown
+    pub fn variant(&self) -> Option<Variant> {
+        Variant::ALL.into_iter().find(|variant| variant.device_id() == *self)
+    }
+
+    /// True if all bytes are 0x00 or 0xFF, which indicates that no chip responded
+    pub fn is_blank(&self) -> bool {
+        is_blank(&[self.manufacturer, self.device])
+    }
+}
+
+/// True if all bytes are 0x00 or 0xFF
+fn is_blank(data: &[u8]) -> bool {
+    data.iter().all(|byte| *byte == 0x00) || data.iter().all(|byte| *byte == 0xff)
+}
@@ -27,6 +27,96 @@
 //! device.set_non_blocking();
 //! ````
 //!
+//! WP and HOLD pins may be of different [types](ControlPin). Pins tied high in hardware are replaced by
+//! [NoPin]. As BPL has no effect while WP# is high, locking the status register fails then.
+//!
+//! ````
+//!# use mc_sst25::device::{Flash, Memory, NoPin};
+//!# use mc_sst25::example::MockBus;
+//!#
+//!# let bus = MockBus::default();
+//!#
+//! let mut device = Flash::new(bus, NoPin::default(), NoPin::default());
+//! let status = device.read_status().unwrap();
+//! ````
+//!
+//! Bus and pins are returned by [Flash::release] after restoring a safe chip state, e.g. for handing
+//! the bus over to a bootloader:
+//!
+//! ````
+//!# use mc_sst25::device::{Flash, Memory};
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//! let device = Flash::new(bus, pin_wp, pin_hold);
+//!
+//! let (bus, pin_wp, pin_hold) = device.release().map_err(|(_, error)| error).unwrap();
+//! ````
+//!
+//! ## Timeouts
+//!
+//! By default, blocking waits poll the status register until the chip is ready, which never ends if
+//! the chip stops responding. Waits may be limited by [timeouts](Timeouts), which default to the
+//! datasheet maxima of the respective operation. The given [DelayNs] is used between the status polls.
+//! The default values match SST25VF080B, [Timeouts::for_variant] returns the values of other chips.
+//!
+//! ````
+//!# use mc_sst25::device::{Flash, Memory, Timeouts};
+//!# use mc_sst25::example::{MockBus, MockDelay, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!# let delay = MockDelay::default();
+//!#
+//! let mut device = Flash::new(bus, pin_wp, pin_hold).with_timeouts(delay, Timeouts::default());
+//!
+//! // Returns CommandError::Timeout, if the chip is still busy after 25 ms
+//! device.erase_sector(0x0).unwrap();
+//! ````
+//!
+//! ## Chip variant
+//!
+//! Addresses and commands are validated against the configured [chip variant](crate::chip::Variant).
+//! SST25VF080B is assumed by default.
+//!
+//! ````
+//!# use mc_sst25::chip::Variant;
+//!# use mc_sst25::device::{CommandError, Flash, Memory};
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//! let mut device = Flash::new(bus, pin_wp, pin_hold);
+//! device.set_variant(Variant::SST25VF040B);
+//!
+//! // Address is beyond the capacity of 512 KByte
+//! let result = device.read::<4>(0x7fffe);
+//! assert!(matches!(result, Err(CommandError::InvalidAddress)));
+//! ````
+//!
+//! ## Probing
+//!
+//! Alternatively the chip variant may be detected at runtime by reading the chip ID.
+//!
+//! ````
+//!# use mc_sst25::chip::Variant;
+//!# use mc_sst25::device::Flash;
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//! let device = Flash::probe(bus, pin_wp, pin_hold).unwrap();
+//! assert_eq!(Variant::SST25VF080B, device.variant());
+//! ````
+//!
 //! ## Reading status
 //!
 //! The device contains eight status bits, which are mapped to [Status] struct.
@@ -53,6 +143,10 @@
 //! The following status flags are used for (write) protecting memory segments.
 //! On device power-up all memory blocks are protected.
 //!
+//! The status is read back after writing. If the written bits did not stick,
+//! [CommandError::StatusWriteFailed] is returned, or [CommandError::StatusLocked] if BPL is set while
+//! WP# is asserted (s. [Status lock](#status-lock)).
+//!
 //! ````
 //!# use mc_sst25::device::{Flash, Memory, Status};
 //!# use mc_sst25::example::{MockBus, MockPin};
@@ -73,6 +167,56 @@
 //! device.write_status(status).unwrap();
 //! ````
 //!
+//! ## Range protection
+//!
+//! Instead of setting the BP bits manually, the protected address range may be given directly.
+//! The range needs to match one of the upper memory fractions supported by the chip variant
+//! (s. [Variant::protected_range](crate::chip::Variant::protected_range)), otherwise
+//! [CommandError::UnsupportedRange] is returned.
+//!
+//! ````
+//!# use mc_sst25::device::{Flash, Memory};
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//!# let mut device = Flash::new(bus, pin_wp, pin_hold);
+//!#
+//! // Protecting the upper 1/16 of SST25VF080B
+//! device.protect_range(0xf0000..0x100000).unwrap();
+//! assert_eq!(0xf0000..0x100000, device.protected_range().unwrap());
+//!
+//! // Removing the protection
+//! device.protect_range(0x0..0x0).unwrap();
+//! ````
+//!
+//! ## Status lock
+//!
+//! The block-protection bits may be locked by setting BPL while asserting WP#, so that the protected
+//! range can not be changed by software anymore. WP# is asserted (driven low) by default.
+//! Once locked, status writes are rejected with [CommandError::StatusLocked].
+//!
+//! ````
+//!# use mc_sst25::device::{Flash, LockState, Memory};
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//!# let mut device = Flash::new(bus, pin_wp, pin_hold);
+//!#
+//! device.protect_range(0xf0000..0x100000).unwrap();
+//! device.lock_status().unwrap();
+//! assert_eq!(LockState::Locked, device.lock_state().unwrap());
+//!
+//! // Releasing WP# and clearing BPL
+//! device.unlock_status().unwrap();
+//! assert_eq!(LockState::Unlocked, device.lock_state().unwrap());
+//! ````
+//!
 //! ## Writing single bytes
 //!
 //! The following method is used for writing single bytes.
@@ -117,6 +261,74 @@
 //! device.aai_program(0x5, &[0x1, 0x2, 0x3, 0x4]).unwrap();
 //! ````
 //!
+//! ### Hardware end-of-write detection
+//!
+//! Instead of polling the status register after each word, the chip may signal the end of each
+//! write on the SO line. The signal is sampled by the SPI device implementing [BusyLine].
+//!
+//! The chip drives SO only while CE# is asserted, otherwise the line is tri-stated. So sampling
+//! requires control of chip select, which is owned by the SPI device. [OwnedBus](crate::bus::OwnedBus)
+//! combines a driver-owned SPI bus with CE# and MISO pins for this purpose:
+//!
+//! ````
+//!# use mc_sst25::bus::OwnedBus;
+//!# use mc_sst25::device::{Flash, Memory, Status};
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let spi = MockBus::default();
+//!# let pin_cs = MockPin::default();
+//!# let pin_miso = MockPin::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//! let bus = OwnedBus::new(spi, pin_cs, pin_miso);
+//! let mut device = Flash::new(bus, pin_wp, pin_hold);
+//!
+//! device.aai_program_with_busy_line(0x6, &[0x1, 0x2, 0x3, 0x4]).unwrap();
+//! ````
+//!
+//! ## Writing data of any length
+//!
+//! Data of any length may be written at any address. Leading and trailing bytes not fitting the
+//! word alignment of AAI programming are written by Byte-Program, the remaining bulk by AAI.
+//! Write operations are disabled at the end, also in case of an error.
+//! ````
+//!# use mc_sst25::device::{Flash, Memory, Status};
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//!# let mut device = Flash::new(bus, pin_wp, pin_hold);
+//!#
+//! // Byte 0x1 is written by Byte-Program, bytes 0x2 to 0x5 by AAI
+//! device.write(0x5, &[0x1, 0x2, 0x3, 0x4, 0x5]).unwrap();
+//! ````
+//!
+//! ## Verified writing
+//!
+//! Writes to protected blocks are silently ignored by the chip. The verified variants of the
+//! program operations read the data back afterward in chunks of [READ_CHUNK_SIZE] bytes and return
+//! [CommandError::VerifyFailed] with the address of the first mismatching byte. Programming is
+//! always done in blocking mode, as the chip can not be read while busy.
+//! ````
+//!# use mc_sst25::device::{CommandError, Flash, Memory};
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//!# let mut device = Flash::new(bus, pin_wp, pin_hold);
+//!#
+//! device.write_verified(0x0, &[0xa, 0xb, 0xc, 0xd]).unwrap();
+//!
+//! // Byte was not programmed, e.g. because the block is protected
+//! let error = device.byte_program_verified(0x10, 0x66).unwrap_err();
+//! assert!(matches!(error, CommandError::VerifyFailed { address: 0x10 }));
+//! ````
+//!
 //! ## Sector erase
 //!
 //! The chip supports erasing single sectors. One sector has the size of 4 KByte.
@@ -137,6 +349,51 @@
 //! device.erase_sector(0x8000).unwrap();
 //! ````
 //!
+//! ## Block erase
+//!
+//! Larger regions may be erased more efficiently by erasing 32 KByte or 64 KByte blocks.
+//! The given address needs to be aligned to the block size.
+//!
+//! *Note: All memory blocks needs to be unprotected (s. [Reading status](#reading-status)), otherwise
+//! erase operation is ignored by device*
+//! ````
+//!# use mc_sst25::device::{Flash, Memory, Status};
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//!# let mut device = Flash::new(bus, pin_wp, pin_hold);
+//!#
+//! // Erases 0x8000 - 0xFFFF
+//! device.erase_block_32k(0x8000).unwrap();
+//!
+//! // Erases 0x10000 - 0x1FFFF
+//! device.erase_block_64k(0x10000).unwrap();
+//! ````
+//!
+//! ## Range erase
+//!
+//! Erases a sector aligned range using the minimal sequence of full chip, block and sector erase
+//! operations. For executing the single steps manually see [erase planning](crate::erase).
+//!
+//! *Note: All memory blocks needs to be unprotected (s. [Reading status](#reading-status)), otherwise
+//! erase operation is ignored by device*
+//! ````
+//!# use mc_sst25::device::{Flash, Memory, Status};
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//!# let mut device = Flash::new(bus, pin_wp, pin_hold);
+//!#
+//! // Erases one sector, one 32 KByte block and one 64 KByte block
+//! device.erase_range(0x7000, 0x20000).unwrap();
+//! ````
+//!
 //! ## Full chip erase
 //!
 //! The chip supports erasing the entire memory.
@@ -161,11 +418,14 @@
 //! Reading an arbitrary amount of data starting at the given address. The data amount is determined
 //! by the generic const L.
 //!
-//! *Note: If the maximum address is within range, the chip wraps automatically and continuous
-//! at the first address.*
+//! By default, the Read command (0x03) is used. For higher SPI clock frequencies the High-Speed-Read
+//! command (0x0B) may be selected by [Flash::set_read_mode], which applies to all read operations.
+//!
+//! *Note: Reading beyond the capacity of the configured chip variant is rejected with
+//! [CommandError::InvalidAddress].*
 //!
 //! ````
-//!# use mc_sst25::device::{Flash, Status, Memory};
+//!# use mc_sst25::device::{Flash, Status, Memory, ReadMode};
 //!# use mc_sst25::example::{MockBus, MockPin};
 //!#
 //!# let bus = MockBus::default();
@@ -177,10 +437,100 @@
 //! // Reading four bytes starting at address 0x0
 //! let data = device.read::<4>(0x0).unwrap();
 //! assert_eq!([0xa, 0xb, 0xc, 0xd], data);
+//!
+//! // Reading using High-Speed-Read command
+//! device.set_read_mode(ReadMode::HighSpeed);
+//! let data = device.read::<4>(0x0).unwrap();
+//! assert_eq!([0xa, 0xb, 0xc, 0xd], data);
+//! ````
+//!
+//! Data of runtime length may be read into a caller provided buffer. Larger ranges can be streamed
+//! in chunks of [READ_CHUNK_SIZE] bytes, so that no large buffer is needed.
+//!
+//! ````
+//!# use mc_sst25::device::{Flash, Status, Memory};
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//!# let mut device = Flash::new(bus, pin_wp, pin_hold);
+//!#
+//! // Reading four bytes starting at address 0x0
+//! let mut buffer = [0x0; 4];
+//! device.read_into(0x0, &mut buffer).unwrap();
+//! assert_eq!([0xa, 0xb, 0xc, 0xd], buffer);
+//!
+//! // Streaming 4 KByte starting at address 0x1000
+//! let mut checksum = 0u8;
+//! device.read_with(0x1000, 4096, |chunk| {
+//!     checksum = chunk.iter().fold(checksum, |sum, byte| sum.wrapping_add(*byte));
+//! }).unwrap();
+//! ````
+//!
+//! ## Blank check
+//!
+//! Ranges may be checked for being erased (all bytes 0xFF), e.g. for verifying an erase operation.
+//! The range is read in chunks of [READ_CHUNK_SIZE] bytes, so no buffer is needed.
+//!
+//! ````
+//!# use mc_sst25::device::{Flash, Memory};
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//!# let mut device = Flash::new(bus, pin_wp, pin_hold);
+//!#
+//! assert!(device.is_erased(0x1000..0x2000).unwrap());
+//!
+//! // Address of the first byte not being 0xFF
+//! assert_eq!(Some(0x0), device.find_first_non_blank(0x0..0x4).unwrap());
+//! ````
+//!
+//! ## Sharing the bus
+//!
+//! Each command is executed within a single [SpiDevice] transaction, which asserts CE# for its
+//! whole duration and releases it afterward. So a longer read blocks a shared bus until completed.
+//! A [Reader] splits the read into chunks, each within its own transaction. CE# is released between
+//! the chunks, so other devices may be serviced meanwhile, and the read continues at the next address.
+//!
+//! ````
+//!# use mc_sst25::device::{Flash, Memory};
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//!# let mut device = Flash::new(bus, pin_wp, pin_hold);
+//!#
+//! let mut reader = device.reader(0x1000, 4096).unwrap();
+//! let mut buffer = [0x0; 256];
+//!
+//! while !reader.is_completed() {
+//!     let length = reader.read_next(&mut device, &mut buffer).unwrap();
+//!     let _chunk = &buffer[..length];
+//!
+//!     // CE# is released, so other devices on the same bus may be serviced here
+//! }
 //! ````
+//!
+//! *Note: HOLD# only takes effect while CE# is low. As CE# is released at the end of each
+//! transaction, pausing the chip within a transaction can not be achieved through [SpiDevice]. So
+//! HOLD# is kept deasserted.*
+use crate::chip::{ChipId, DeviceId, Variant, BLOCK_32K_SIZE, BLOCK_64K_SIZE, SECTOR_SIZE};
+use crate::erase::{ErasePlan, EraseStep};
+use crate::pending::{AaiWriter, BlockErase, ByteProgram, ChipErase, Pending, SectorErase};
+use core::convert::Infallible;
 use core::fmt::{Debug, Formatter};
+use core::ops::Range;
+use embedded_hal::delay::DelayNs;
 use embedded_hal::digital::OutputPin;
 use embedded_hal::spi::{Operation, SpiDevice};
+use embedded_hal::{digital, spi};
 
 /// General flash memory interface
 pub trait Memory {
@@ -201,13 +551,19 @@ pub trait Memory {
     /// Enables write operations
     fn write_disable(&mut self) -> Result<(), Self::Error>;
 
-    /// Writes the given status to status registers
+    /// Writes the given status to status registers and verifies the written bits
     fn write_status(&mut self, status: Status) -> Result<(), Self::Error>;
 
     /// The Sector-Erase instruction clears all bits in the selected 4 KByte sector to FFH.
     /// A Sector-Erase instruction applied to a protected memory area will be ignored
     fn erase_sector(&mut self, address: u32) -> Result<(), Self::Error>;
 
+    /// Clears all bits in the selected 32 KByte block to FFH. Address needs to be block aligned.
+    fn erase_block_32k(&mut self, address: u32) -> Result<(), Self::Error>;
+
+    /// Clears all bits in the selected 64 KByte block to FFH. Address needs to be block aligned.
+    fn erase_block_64k(&mut self, address: u32) -> Result<(), Self::Error>;
+
     /// Erases the full chip.
     fn erase_full(&mut self) -> Result<(), Self::Error>;
 
@@ -217,100 +573,449 @@ pub trait Memory {
     /// Auto address increment (AAI) programming for writing larger amount of data
     fn aai_program(&mut self, address: u32, buffer: &[u8]) -> Result<(), Self::Error>;
 
+    /// Writes data of any length starting at any address
+    fn write(&mut self, address: u32, data: &[u8]) -> Result<(), Self::Error>;
+
     /// Reads data with length L starting at the given address
     fn read<const L: usize>(&mut self, address: u32) -> Result<[u8; L], Self::Error>;
+
+    /// Reads data starting at the given address, filling the given buffer
+    fn read_into(&mut self, address: u32, buffer: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Reads data with the given length starting at the given address in chunks of at most
+    /// [READ_CHUNK_SIZE] bytes. The given function is called for each chunk in order.
+    fn read_with<F: FnMut(&[u8])>(&mut self, address: u32, length: usize, f: F) -> Result<(), Self::Error>;
+
+    /// Reads the JEDEC ID (manufacturer, memory type and capacity)
+    fn read_jedec_id(&mut self) -> Result<ChipId, Self::Error>;
+
+    /// Reads manufacturer and device ID using the legacy Read-ID command
+    fn read_id(&mut self) -> Result<DeviceId, Self::Error>;
+}
+
+/// SPI device able to sample the hardware end-of-write signal, which is output by the chip on the
+/// SO line during AAI programming once enabled.
+///
+/// The chip drives SO only while CE# is asserted and tri-states the line otherwise. So sampling
+/// requires control of chip select, which is owned by the SPI device. [OwnedBus](crate::bus::OwnedBus)
+/// implements this for a driver-owned bus.
+pub trait BusyLine: spi::ErrorType {
+    /// Asserts CE#, samples the MISO level and releases CE# again. Returns true if the chip is still
+    /// busy, i.e. the SO line is low.
+    fn is_busy(&mut self) -> Result<bool, Self::Error>;
 }
 
 /// SS25* flash memory chip
-pub struct Flash<B: SpiDevice<u8>, P: OutputPin> {
+pub struct Flash<B: SpiDevice<u8>, W: ControlPin, H: ControlPin = W, D: DelayNs = NoDelay> {
     /// SPI bus
     bus: B,
 
     /// GPIO WP pin
-    pin_write_protection: P,
+    pin_write_protection: W,
 
     /// GPIO Hold pin
-    pin_hold: P,
+    pin_hold: H,
 
     /// Is the device configured?
     configured: bool,
 
+    /// True if WP# is driven low, so that a set BPL bit makes the status register read-only
+    write_protection: bool,
+
+    /// True if the chip is in AAI mode, so that only RDSR, WRDI and AAI commands are accepted
+    aai_active: bool,
+
     /// True if blocks on longer lasting operations
     blocking: bool,
-}
 
-/// Error when communicating with the device
-#[derive(PartialEq, Eq)]
-pub enum CommandError<B: SpiDevice<u8>, P: OutputPin> {
-    /// SPI transfer error
-    TransferError(B::Error),
+    /// Chip variant used for address and command validation
+    variant: Variant,
 
-    /// Error while setting GPIO state of HOLD pin
-    HoldPinError(P::Error),
+    /// Command used for reading memory
+    read_mode: ReadMode,
 
-    /// Error while setting GPIO state of WP pin
-    WriteProtectionPinError(P::Error),
+    /// Delay used between status polls if timeouts are enabled
+    delay: D,
 
-    /// Chip is still busy executing another operation
-    Busy,
+    /// Timeouts of blocking waits, None if waiting infinitely
+    timeouts: Option<Timeouts>,
+}
 
-    /// The given memory address is out of range
-    InvalidAddress,
+/// Resumable read of a memory range, which reads one chunk per call within its own transaction. The
+/// reader does not borrow the device, so the bus may be used by other devices between chunks and the
+/// read continues at the next address afterward.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reader {
+    /// Start address
+    address: u32,
 
-    /// The given buffer size is too small for the called operation
-    BufferTooSmall,
+    /// Total number of bytes to read
+    length: usize,
 
-    /// The called operation requires an even buffer size
-    BufferUneven,
+    /// Number of bytes already read
+    offset: usize,
 }
 
-const CMD_AAI_PROGRAM: u8 = 0b1010_1101;
+impl Reader {
+    /// Reads the next chunk into the given buffer. Returns the number of bytes read, which is zero
+    /// once the whole range has been read.
+    pub fn read_next<B: SpiDevice<u8>, W: ControlPin, H: ControlPin, D: DelayNs>(
+        &mut self,
+        flash: &mut Flash<B, W, H, D>,
+        buffer: &mut [u8],
+    ) -> Result<usize, CommandError<B, W, H>>
+    where
+        W::Error: Debug,
+        H::Error: Debug,
+    {
+        let length = buffer.len().min(self.remaining());
+        if length == 0 {
+            return Ok(0);
+        }
 
-impl<B: SpiDevice<u8>, P: OutputPin> Memory for Flash<B, P>
-where
-    P::Error: Debug,
-{
-    type Error = CommandError<B, P>;
+        flash.read_data(self.next_address(), &mut buffer[..length])?;
+        self.offset += length;
 
-    /// Switches to blocking mode
-    fn set_blocking(&mut self) {
-        self.blocking = true;
+        Ok(length)
     }
 
-    /// Switches to non-blocking mode
-    fn set_non_blocking(&mut self) {
-        self.blocking = false;
+    /// Returns the number of bytes already read
+    pub fn offset(&self) -> usize {
+        self.offset
     }
 
-    /// Reads and returns the status registers
-    fn read_status(&mut self) -> Result<Status, CommandError<B, P>> {
-        self.configure()?;
-        let mut buffer = [0x0];
-        self.bus
-            .transaction(&mut [Operation::Write(&[0b0000_0101]), Operation::Read(&mut buffer)])
-            .map_err(CommandError::TransferError)?;
-
-        Ok(Status::from_register(buffer[0]))
+    /// Returns the number of bytes left for reading
+    pub fn remaining(&self) -> usize {
+        self.length - self.offset
     }
 
-    /// Enables write operations
-    fn write_enable(&mut self) -> Result<(), CommandError<B, P>> {
-        self.write(&mut [0b0000_0110])?;
-        Ok(())
+    /// Returns the address of the next byte to read
+    pub fn next_address(&self) -> u32 {
+        self.address + self.offset as u32
     }
 
-    /// Enables write operations
-    fn write_disable(&mut self) -> Result<(), CommandError<B, P>> {
-        self.write(&mut [0b0000_0100])?;
-        Ok(())
+    /// True if the whole range has been read
+    pub fn is_completed(&self) -> bool {
+        self.remaining() == 0
     }
+}
 
-    /// Writes the given status to status registers
-    fn write_status(&mut self, status: Status) -> Result<(), CommandError<B, P>> {
-        self.write_enable()?;
+/// Effective lock state of the status register, given by BPL bit and WP# pin
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LockState {
+    /// BPL is not set, so the status register is writable
+    Unlocked,
+
+    /// BPL is set, but WP# is not asserted, so the status register is still writable
+    Armed,
+
+    /// BPL is set while WP# is asserted, so the status register is read-only
+    Locked,
+}
+
+/// Command used for reading memory
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ReadMode {
+    /// Read command (0x03), supports clock frequencies up to 25/33 MHz depending on variant
+    #[default]
+    Normal,
+
+    /// High-Speed-Read command (0x0B) with one dummy byte, supports clock frequencies up to 50/80 MHz
+    /// depending on variant
+    HighSpeed,
+}
+
+/// Maximum durations of blocking waits. Defaults to the datasheet maxima of SST25VF080B, s.
+/// [Timeouts::for_variant] for other chips.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Timeouts {
+    /// Maximum duration of a sector erase in microseconds
+    pub sector_erase_us: u32,
+
+    /// Maximum duration of a 32 KByte or 64 KByte block erase in microseconds
+    pub block_erase_us: u32,
+
+    /// Maximum duration of a full chip erase in microseconds
+    pub chip_erase_us: u32,
+
+    /// Maximum duration of programming a byte, an AAI word or the status register in microseconds.
+    /// Variants without AAI program a single byte by Page-Program, which takes considerably longer.
+    pub byte_program_us: u32,
 
-        self.bus.write(&[0x0]).map_err(CommandError::TransferError)?;
-        self.write(&mut [0b0000_0001, status.to_registers()])?;
+    /// Delay between two status polls in microseconds
+    pub poll_interval_us: u32,
+}
+
+impl Timeouts {
+    /// Returns the datasheet maxima (TSE, TBE, TSCE and TBP, or TPP for Page-Program variants) of
+    /// the given chip variant
+    pub const fn for_variant(variant: Variant) -> Self {
+        match variant {
+            Variant::SST25VF010A => Self::new(25_000, 25_000, 100_000, 20),
+            Variant::SST25VF020B
+            | Variant::SST25VF040B
+            | Variant::SST25VF080B
+            | Variant::SST25VF016B
+            | Variant::SST25VF032B => Self::new(25_000, 25_000, 50_000, 10),
+            Variant::SST25VF064C | Variant::SST25PF040C => Self::new(25_000, 25_000, 50_000, 2_500),
+            Variant::SST25WF512
+            | Variant::SST25WF010
+            | Variant::SST25WF020
+            | Variant::SST25WF040
+            | Variant::SST25WF080 => Self::new(150_000, 150_000, 250_000, 60),
+        }
+    }
+
+    /// Creates timeouts using the default poll interval
+    const fn new(
+        sector_erase_us: u32,
+        block_erase_us: u32,
+        chip_erase_us: u32,
+        byte_program_us: u32,
+    ) -> Self {
+        Self {
+            sector_erase_us,
+            block_erase_us,
+            chip_erase_us,
+            byte_program_us,
+            poll_interval_us: 10,
+        }
+    }
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self::for_variant(Variant::SST25VF080B)
+    }
+}
+
+/// Placeholder delay of devices without timeouts
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NoDelay {}
+
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+/// Pin connected to WP# or HOLD#. Implemented for any [OutputPin] and for [NoPin].
+pub trait ControlPin: digital::ErrorType {
+    /// True if the pin state is driven by the MCU, false if tied high in hardware
+    const DRIVEN: bool;
+
+    /// Drives the pin low
+    fn set_low(&mut self) -> Result<(), Self::Error>;
+
+    /// Drives the pin high
+    fn set_high(&mut self) -> Result<(), Self::Error>;
+}
+
+impl<T: OutputPin> ControlPin for T {
+    const DRIVEN: bool = true;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        OutputPin::set_low(self)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        OutputPin::set_high(self)
+    }
+}
+
+/// Placeholder of WP# or HOLD# pins, which are tied high in hardware instead of being connected to
+/// a GPIO. Setting the pin state has no effect. As BPL has no effect while WP# is high, the status
+/// register can not be [locked](Flash::lock_status) and is never reported as [locked](LockState::Locked).
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NoPin {}
+
+impl digital::ErrorType for NoPin {
+    type Error = Infallible;
+}
+
+impl ControlPin for NoPin {
+    const DRIVEN: bool = false;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Operation waited for, determines the applicable timeout
+#[derive(Copy, Clone)]
+pub(crate) enum WaitFor {
+    SectorErase,
+    BlockErase,
+    ChipErase,
+    Program,
+}
+
+impl WaitFor {
+    /// Returns the matching timeout in microseconds
+    pub(crate) fn timeout(self, timeouts: &Timeouts) -> u32 {
+        match self {
+            WaitFor::SectorErase => timeouts.sector_erase_us,
+            WaitFor::BlockErase => timeouts.block_erase_us,
+            WaitFor::ChipErase => timeouts.chip_erase_us,
+            WaitFor::Program => timeouts.byte_program_us,
+        }
+    }
+}
+
+impl From<EraseStep> for WaitFor {
+    fn from(step: EraseStep) -> Self {
+        match step {
+            EraseStep::Full => WaitFor::ChipErase,
+            EraseStep::Block64K(_) | EraseStep::Block32K(_) => WaitFor::BlockErase,
+            EraseStep::Sector(_) => WaitFor::SectorErase,
+        }
+    }
+}
+
+/// Error when communicating with the device
+#[derive(PartialEq, Eq)]
+pub enum CommandError<B: spi::ErrorType, W: digital::ErrorType, H: digital::ErrorType = W> {
+    /// SPI transfer error
+    TransferError(B::Error),
+
+    /// Error while setting GPIO state of HOLD pin
+    HoldPinError(H::Error),
+
+    /// Error while setting GPIO state of WP pin
+    WriteProtectionPinError(W::Error),
+
+    /// Error while sampling the busy line during hardware end-of-write detection
+    BusyLineError(B::Error),
+
+    /// Chip is still busy executing another operation or in AAI mode, e.g. by an active
+    /// [AaiWriter], which needs to be paused or completed first
+    Busy,
+
+    /// The given memory address is out of range
+    InvalidAddress,
+
+    /// The given buffer size is too small for the called operation
+    BufferTooSmall,
+
+    /// The called operation requires an even buffer size
+    BufferUneven,
+
+    /// The given memory address is not aligned to the boundary required by the called operation
+    UnalignedAddress,
+
+    /// The called command is not supported by the configured chip variant or the hardware, e.g.
+    /// locking the status register while WP# is tied high
+    UnsupportedCommand,
+
+    /// The written status bits did not match the status read back afterward
+    StatusWriteFailed,
+
+    /// The status register could not be written, as it is locked by BPL while WP# is asserted
+    StatusLocked,
+
+    /// No chip responded, the ID consisted of 0x00 or 0xFF bytes only (e.g. floating MISO line)
+    NoChip,
+
+    /// The chip returned an ID not matching any known variant
+    UnknownChip(ChipId),
+
+    /// The chip was still busy after the configured timeout expired
+    Timeout,
+
+    /// The given address range can not be expressed by the block-protection bits of the configured
+    /// chip variant
+    UnsupportedRange,
+
+    /// The data read back after programming did not match the written data
+    VerifyFailed {
+        /// Address of the first mismatching byte
+        address: u32,
+    },
+}
+
+/// Maximum chunk size of [Memory::read_with] in bytes
+pub const READ_CHUNK_SIZE: usize = 64;
+
+pub(crate) const CMD_READ_STATUS: u8 = 0b0000_0101;
+pub(crate) const CMD_WRITE_ENABLE: u8 = 0b0000_0110;
+pub(crate) const CMD_WRITE_DISABLE: u8 = 0b0000_0100;
+pub(crate) const CMD_SECTOR_ERASE: u8 = 0b0010_0000;
+pub(crate) const CMD_CHIP_ERASE: u8 = 0b0110_0000;
+pub(crate) const CMD_BYTE_PROGRAM: u8 = 0b0000_0010;
+pub(crate) const CMD_AAI_PROGRAM: u8 = 0b1010_1101;
+pub(crate) const CMD_ENABLE_WRITE_STATUS: u8 = 0b0101_0000;
+pub(crate) const CMD_WRITE_STATUS: u8 = 0b0000_0001;
+pub(crate) const CMD_ENABLE_SO_BUSY: u8 = 0b0111_0000;
+pub(crate) const CMD_DISABLE_SO_BUSY: u8 = 0b1000_0000;
+pub(crate) const CMD_READ: u8 = 0b0000_0011;
+pub(crate) const CMD_HIGH_SPEED_READ: u8 = 0b0000_1011;
+pub(crate) const CMD_BLOCK_ERASE_32K: u8 = 0b0101_0010;
+pub(crate) const CMD_BLOCK_ERASE_64K: u8 = 0b1101_1000;
+pub(crate) const CMD_READ_JEDEC_ID: u8 = 0b1001_1111;
+pub(crate) const CMD_READ_ID: u8 = 0b1001_0000;
+
+impl<B: SpiDevice<u8>, W: ControlPin, H: ControlPin, D: DelayNs> Memory for Flash<B, W, H, D>
+where
+    W::Error: Debug,
+    H::Error: Debug,
+{
+    type Error = CommandError<B, W, H>;
+
+    /// Switches to blocking mode
+    fn set_blocking(&mut self) {
+        self.blocking = true;
+    }
+
+    /// Switches to non-blocking mode
+    fn set_non_blocking(&mut self) {
+        self.blocking = false;
+    }
+
+    /// Reads and returns the status registers
+    fn read_status(&mut self) -> Result<Status, CommandError<B, W, H>> {
+        let mut buffer = [0x0];
+        self.command_response(&[CMD_READ_STATUS], &mut buffer)?;
+
+        Ok(Status::from_register(buffer[0]))
+    }
+
+    /// Enables write operations
+    fn write_enable(&mut self) -> Result<(), CommandError<B, W, H>> {
+        self.command(&[CMD_WRITE_ENABLE])?;
+        Ok(())
+    }
+
+    /// Enables write operations
+    fn write_disable(&mut self) -> Result<(), CommandError<B, W, H>> {
+        self.command(&[CMD_WRITE_DISABLE])?;
+        Ok(())
+    }
+
+    /// Writes the given status to status registers.
+    /// The status register is write-enabled by EWSR or WREN instruction depending on the variant.
+    /// The written bits are verified by reading the status back afterward. Only the bits implemented
+    /// by the variant are compared (s. [Variant::status_mask]).
+    fn write_status(&mut self, status: Status) -> Result<(), CommandError<B, W, H>> {
+        if self.variant.commands().enable_write_status {
+            self.command(&[CMD_ENABLE_WRITE_STATUS])?;
+        } else {
+            self.write_enable()?;
+        }
+
+        self.command(&[CMD_WRITE_STATUS, status.to_registers()])?;
+        self.wait(true, WaitFor::Program)?;
+
+        let mask = self.variant.status_mask();
+        let actual = self.read_status()?;
+        if actual.to_registers() & mask != status.to_registers() & mask {
+            if actual.bits_read_only && self.write_protection {
+                return Err(CommandError::StatusLocked);
+            }
+
+            return Err(CommandError::StatusWriteFailed);
+        }
 
         Ok(())
     }
@@ -318,143 +1023,713 @@ where
     /// The Sector-Erase instruction clears all bits in the selected 4 KByte sector to FFH.
     /// A Sector-Erase instruction applied to a protected memory area will be ignored
     fn erase_sector(&mut self, address: u32) -> Result<(), Self::Error> {
-        self.assert_valid_address(address)?;
+        assert_valid_range(self.variant, address, 1)?;
 
         self.write_enable()?;
         self.assert_not_busy()?;
 
-        let mut frame = [0b0010_0000, 0x0, 0x0, 0x0];
-        self.address_command(address, &mut frame);
-        self.write(&mut frame)?;
+        let mut frame = [CMD_SECTOR_ERASE, 0x0, 0x0, 0x0];
+        address_command(address, &mut frame);
+        self.command(&frame)?;
+
+        self.wait(false, WaitFor::SectorErase)
+    }
+
+    /// Clears all bits in the selected 32 KByte block to FFH. Address needs to be block aligned.
+    /// Waits until operation is completed in blocking mode, otherwise returns when command is sent
+    fn erase_block_32k(&mut self, address: u32) -> Result<(), CommandError<B, W, H>> {
+        let frame = block_erase_frame(self.variant, CMD_BLOCK_ERASE_32K, address)?;
+        self.erase_block(&frame)
+    }
 
-        self.wait(false)
+    /// Clears all bits in the selected 64 KByte block to FFH. Address needs to be block aligned.
+    /// Waits until operation is completed in blocking mode, otherwise returns when command is sent
+    fn erase_block_64k(&mut self, address: u32) -> Result<(), CommandError<B, W, H>> {
+        let frame = block_erase_frame(self.variant, CMD_BLOCK_ERASE_64K, address)?;
+        self.erase_block(&frame)
     }
 
     /// Erases the full chip.
     /// Waits until operation is completed in blocking mode, otherwise returns when command is sent
-    fn erase_full(&mut self) -> Result<(), CommandError<B, P>> {
+    fn erase_full(&mut self) -> Result<(), CommandError<B, W, H>> {
         self.write_enable()?;
         self.assert_not_busy()?;
 
-        self.write(&mut [0b0110_0000])?;
-        self.wait(false)
+        self.command(&[CMD_CHIP_ERASE])?;
+        self.wait(false, WaitFor::ChipErase)
     }
 
     /// Programs/Writes the given byte at the given address. Disables internal write protection.
     /// Waits until operation is completed in blocking mode, otherwise returns when command is sent
-    fn byte_program(&mut self, address: u32, data: u8) -> Result<(), CommandError<B, P>> {
-        self.assert_valid_address(address)?;
+    fn byte_program(&mut self, address: u32, data: u8) -> Result<(), CommandError<B, W, H>> {
+        assert_valid_range(self.variant, address, 1)?;
 
         self.write_enable()?;
         self.assert_not_busy()?;
 
-        let mut frame = [0b0000_0010, 0x0, 0x0, 0x0, data];
-        self.address_command(address, &mut frame);
+        let mut frame = [CMD_BYTE_PROGRAM, 0x0, 0x0, 0x0, data];
+        address_command(address, &mut frame);
 
-        self.write(&mut frame)?;
-        self.wait(false)
+        self.command(&frame)?;
+        self.wait(false, WaitFor::Program)
     }
 
     /// Auto address increment (AAI) programming for writing larger amount of data
     /// Buffer needs to contain at least two bytes and an even data amount
-    fn aai_program(&mut self, address: u32, buffer: &[u8]) -> Result<(), CommandError<B, P>> {
-        self.assert_valid_address(address)?;
+    fn aai_program(&mut self, address: u32, buffer: &[u8]) -> Result<(), CommandError<B, W, H>> {
+        assert_aai_buffer(self.variant, address, buffer)?;
+        self.aai_words(address, buffer, |flash| flash.wait(true, WaitFor::Program))?;
+        self.write_disable()
+    }
 
-        if buffer.len() < 2 {
-            return Err(CommandError::BufferTooSmall);
+    /// Writes data of any length starting at any address. Bytes outside the word aligned bulk are
+    /// written by Byte-Program, the bulk by AAI programming if supported by the variant.
+    /// Write operations are disabled afterward, also in case of an error.
+    /// Waits until the operation is completed, also in non-blocking mode.
+    fn write(&mut self, address: u32, data: &[u8]) -> Result<(), CommandError<B, W, H>> {
+        if data.is_empty() {
+            return Ok(());
         }
 
-        if buffer.len() & 1 == 1 {
-            return Err(CommandError::BufferUneven);
-        }
+        assert_valid_range(self.variant, address, data.len())?;
 
-        self.write_enable()?;
-        self.assert_not_busy()?;
+        let result = self.with_blocking(true, |flash| flash.write_segments(address, data));
+        let disable_result = self.write_disable();
 
-        let mut frame = [CMD_AAI_PROGRAM, 0x0, 0x0, 0x0, buffer[0], buffer[1]];
-        self.address_command(address, &mut frame);
-        self.write(&mut frame)?;
-        self.wait(true)?;
+        result.and(disable_result)
+    }
 
-        for chunk in buffer[2..].chunks(2) {
-            self.write(&mut [CMD_AAI_PROGRAM, chunk[0], chunk[1]])?;
-            self.wait(true)?;
+    /// Reads data with length L starting at the given address
+    fn read<const L: usize>(&mut self, address: u32) -> Result<[u8; L], CommandError<B, W, H>> {
+        let mut buffer = [0x0; L];
+        self.read_data(address, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Reads data starting at the given address, filling the given buffer
+    fn read_into(&mut self, address: u32, buffer: &mut [u8]) -> Result<(), CommandError<B, W, H>> {
+        self.read_data(address, buffer)
+    }
+
+    /// Reads data with the given length starting at the given address in chunks of at most
+    /// [READ_CHUNK_SIZE] bytes. The given function is called for each chunk in order.
+    fn read_with<F: FnMut(&[u8])>(
+        &mut self,
+        address: u32,
+        length: usize,
+        mut f: F,
+    ) -> Result<(), CommandError<B, W, H>> {
+        assert_valid_range(self.variant, address, length)?;
+
+        let mut buffer = [0x0; READ_CHUNK_SIZE];
+        let mut offset = 0;
+
+        while offset < length {
+            let chunk = &mut buffer[..READ_CHUNK_SIZE.min(length - offset)];
+            self.read_data(address + offset as u32, chunk)?;
+            f(chunk);
+
+            offset += chunk.len();
         }
 
-        self.write_disable()
+        Ok(())
     }
 
-    /// Reads data with length L starting at the given address
-    fn read<const L: usize>(&mut self, address: u32) -> Result<[u8; L], CommandError<B, P>> {
-        self.assert_valid_address(address)?;
-        self.configure()?;
+    /// Reads the JEDEC ID (manufacturer, memory type and capacity)
+    fn read_jedec_id(&mut self) -> Result<ChipId, CommandError<B, W, H>> {
+        let mut buffer = [0x0; 3];
+        self.command_response(&[CMD_READ_JEDEC_ID], &mut buffer)?;
 
-        let mut frame = [0b0000_0011, 0x0, 0x0, 0x0];
-        self.address_command(address, &mut frame);
+        Ok(ChipId::from_bytes(buffer))
+    }
 
-        if let Err(error) = self.bus.write(&frame) {
-            return Err(CommandError::TransferError(error));
-        }
+    /// Reads manufacturer and device ID using the legacy Read-ID command.
+    /// Address 0x0 is used, so the manufacturer ID is returned first.
+    fn read_id(&mut self) -> Result<DeviceId, CommandError<B, W, H>> {
+        let mut buffer = [0x0; 2];
+        self.command_response(&[CMD_READ_ID, 0x0, 0x0, 0x0], &mut buffer)?;
 
-        let mut buffer = [0x0; L];
-        self.bus.read(&mut buffer).map_err(CommandError::TransferError)?;
-        Ok(buffer)
+        Ok(DeviceId::from_bytes(buffer))
     }
 }
 
-impl<B: SpiDevice<u8>, P: OutputPin> Flash<B, P>
+impl<B: SpiDevice<u8>, W: ControlPin, H: ControlPin> Flash<B, W, H>
 where
-    P::Error: Debug,
+    W::Error: Debug,
+    H::Error: Debug,
 {
-    pub fn new(bus: B, pin_write_protection: P, pin_hold: P) -> Self {
+    /// Creates a new device instance. SST25VF080B is assumed as chip variant, which may be changed
+    /// by [Flash::set_variant]. Until then, accesses beyond 1 MByte fail with [CommandError::InvalidAddress],
+    /// s. [Flash::probe] for detecting the variant. Blocking waits are not limited, s. [Flash::with_timeouts].
+    pub fn new(bus: B, pin_write_protection: W, pin_hold: H) -> Self {
         Self {
             bus,
             pin_write_protection,
             pin_hold,
             configured: false,
+            write_protection: W::DRIVEN,
+            aai_active: false,
             blocking: true,
+            variant: Variant::SST25VF080B,
+            read_mode: ReadMode::Normal,
+            delay: NoDelay::default(),
+            timeouts: None,
+        }
+    }
+
+    /// Creates a new device instance and identifies the attached chip variant (s. [Flash::detect])
+    pub fn probe(bus: B, pin_write_protection: W, pin_hold: H) -> Result<Self, CommandError<B, W, H>> {
+        let mut device = Self::new(bus, pin_write_protection, pin_hold);
+        device.detect()?;
+
+        Ok(device)
+    }
+}
+
+impl<B: SpiDevice<u8>, W: ControlPin, H: ControlPin, D: DelayNs> Flash<B, W, H, D>
+where
+    W::Error: Debug,
+    H::Error: Debug,
+{
+    /// Limits all blocking waits by the given timeouts. The given delay is used between the status
+    /// polls. Waits exceeding the timeout of the respective operation fail with [CommandError::Timeout].
+    pub fn with_timeouts<T: DelayNs>(self, delay: T, timeouts: Timeouts) -> Flash<B, W, H, T> {
+        Flash {
+            bus: self.bus,
+            pin_write_protection: self.pin_write_protection,
+            pin_hold: self.pin_hold,
+            configured: self.configured,
+            write_protection: self.write_protection,
+            aai_active: self.aai_active,
+            blocking: self.blocking,
+            variant: self.variant,
+            read_mode: self.read_mode,
+            delay,
+            timeouts: Some(timeouts),
+        }
+    }
+
+    /// Restores a safe chip state and returns bus and pins as (bus, WP, HOLD).
+    /// Waits until the chip is idle, exits AAI mode and disables writing.
+    ///
+    /// In case of an error, the device is returned along with the error, so that the release may be
+    /// retried or the peripherals freed without accessing the chip (s. [Flash::free]).
+    #[allow(clippy::type_complexity)]
+    pub fn release(mut self) -> Result<(B, W, H), (Self, CommandError<B, W, H>)> {
+        match self.restore_safe_state() {
+            Ok(()) => Ok(self.free()),
+            Err(error) => Err((self, error)),
+        }
+    }
+
+    /// Returns bus and pins as (bus, WP, HOLD) without accessing the chip
+    pub fn free(self) -> (B, W, H) {
+        (self.bus, self.pin_write_protection, self.pin_hold)
+    }
+
+    /// Returns the timeouts of blocking waits, None if waiting infinitely
+    pub fn timeouts(&self) -> Option<Timeouts> {
+        self.timeouts
+    }
+
+    /// Sets the chip variant, which determines the valid address range and supported commands
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+
+    /// Returns the configured chip variant
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Sets the command used by all memory read operations
+    pub fn set_read_mode(&mut self, mode: ReadMode) {
+        self.read_mode = mode;
+    }
+
+    /// Returns the command used by all memory read operations
+    pub fn read_mode(&self) -> ReadMode {
+        self.read_mode
+    }
+
+    /// Identifies the attached chip by its JEDEC ID and configures the matching variant.
+    /// Falls back to the legacy Read-ID command for variants not supporting JEDEC ID.
+    pub fn detect(&mut self) -> Result<Variant, CommandError<B, W, H>> {
+        let jedec_id = self.read_jedec_id()?;
+
+        let variant = match jedec_id.variant() {
+            Some(variant) => variant,
+            None => identify_legacy(jedec_id, self.read_id()?)?,
+        };
+
+        self.variant = variant;
+        Ok(variant)
+    }
+
+    /// Plans the minimal sequence of erase operations covering exactly the range from start (inclusive)
+    /// to end (exclusive). Both bounds need to be sector aligned.
+    pub fn plan_erase(&self, start: u32, end: u32) -> Result<ErasePlan, CommandError<B, W, H>> {
+        plan_erase(self.variant, start, end)
+    }
+
+    /// Executes a single erase operation, e.g. a step of an [erase plan](Flash::plan_erase).
+    /// Waits until operation is completed in blocking mode, otherwise returns when command is sent
+    pub fn erase(&mut self, step: EraseStep) -> Result<(), CommandError<B, W, H>> {
+        match step {
+            EraseStep::Full => self.erase_full(),
+            EraseStep::Block64K(address) => self.erase_block_64k(address),
+            EraseStep::Block32K(address) => self.erase_block_32k(address),
+            EraseStep::Sector(address) => self.erase_sector(address),
         }
     }
 
-    /// Writes the given data
-    fn write<'a>(&'a mut self, data: &'a mut [u8]) -> Result<(), CommandError<B, P>> {
+    /// Starts erasing the 4 KByte sector at the given address without waiting for completion, also in
+    /// blocking mode. The returned handle needs to be polled until the operation is completed.
+    pub fn start_erase_sector(
+        &mut self,
+        address: u32,
+    ) -> Result<Pending<'_, Self, SectorErase>, CommandError<B, W, H>> {
+        self.with_blocking(false, |flash| flash.erase_sector(address))?;
+        Ok(Pending::new(self))
+    }
+
+    /// Starts erasing the 32 KByte block at the given address without waiting for completion, also in
+    /// blocking mode. The returned handle needs to be polled until the operation is completed.
+    pub fn start_erase_block_32k(
+        &mut self,
+        address: u32,
+    ) -> Result<Pending<'_, Self, BlockErase>, CommandError<B, W, H>> {
+        self.with_blocking(false, |flash| flash.erase_block_32k(address))?;
+        Ok(Pending::new(self))
+    }
+
+    /// Starts erasing the 64 KByte block at the given address without waiting for completion, also in
+    /// blocking mode. The returned handle needs to be polled until the operation is completed.
+    pub fn start_erase_block_64k(
+        &mut self,
+        address: u32,
+    ) -> Result<Pending<'_, Self, BlockErase>, CommandError<B, W, H>> {
+        self.with_blocking(false, |flash| flash.erase_block_64k(address))?;
+        Ok(Pending::new(self))
+    }
+
+    /// Starts erasing the full chip without waiting for completion, also in blocking mode.
+    /// The returned handle needs to be polled until the operation is completed.
+    pub fn start_erase_full(&mut self) -> Result<Pending<'_, Self, ChipErase>, CommandError<B, W, H>> {
+        self.with_blocking(false, |flash| flash.erase_full())?;
+        Ok(Pending::new(self))
+    }
+
+    /// Starts programming the given byte at the given address without waiting for completion, also in
+    /// blocking mode. The returned handle needs to be polled until the operation is completed.
+    pub fn start_byte_program(
+        &mut self,
+        address: u32,
+        data: u8,
+    ) -> Result<Pending<'_, Self, ByteProgram>, CommandError<B, W, H>> {
+        self.with_blocking(false, |flash| flash.byte_program(address, data))?;
+        Ok(Pending::new(self))
+    }
+
+    /// Returns the address range currently write-protected by the block-protection bits
+    pub fn protected_range(&mut self) -> Result<Range<u32>, CommandError<B, W, H>> {
+        let status = self.read_status()?;
+        Ok(self.variant.protected_range(status.block_protection()))
+    }
+
+    /// Write-protects the given address range by writing the matching block-protection bits.
+    /// Only upper fractions of the memory supported by the variant may be protected
+    /// (s. [Variant::protected_range]), an empty range removes the protection. BPL is left unchanged.
+    pub fn protect_range(&mut self, range: Range<u32>) -> Result<(), CommandError<B, W, H>> {
+        let bits = self.variant.protection_bits(range).ok_or(CommandError::UnsupportedRange)?;
+
+        let mut status = self.read_status()?;
+        status.set_block_protection(bits);
+
+        self.write_status(status)
+    }
+
+    /// Drives WP# low (asserted = true) or high. While asserted, a set BPL bit makes the status
+    /// register read-only. Asserting fails with [CommandError::UnsupportedCommand] if WP# is tied high
+    /// in hardware ([NoPin]).
+    pub fn set_write_protection(&mut self, asserted: bool) -> Result<(), CommandError<B, W, H>> {
+        if asserted && !W::DRIVEN {
+            return Err(CommandError::UnsupportedCommand);
+        }
+
         self.configure()?;
-        self.bus.write(data).map_err(CommandError::TransferError)
+
+        if asserted {
+            self.pin_write_protection.set_low()
+        } else {
+            self.pin_write_protection.set_high()
+        }
+        .map_err(CommandError::WriteProtectionPinError)?;
+
+        self.write_protection = asserted;
+        Ok(())
     }
 
-    /// Adds the given memory address to the command frame
-    fn address_command(&mut self, address: u32, frame: &mut [u8]) {
-        frame[1] = (address >> 16) as u8;
-        frame[2] = (address >> 8) as u8;
-        frame[3] = address as u8;
+    /// Returns true if WP# is asserted (driven low)
+    pub fn write_protection(&self) -> bool {
+        self.write_protection
     }
 
-    /// Returns an error in case device is busy
-    fn assert_not_busy(&mut self) -> Result<(), CommandError<B, P>> {
-        if self.read_status()?.busy {
+    /// Locks the status register, so that the block-protection bits can not be changed anymore.
+    /// Sets BPL while keeping the remaining status bits and asserts WP# afterward.
+    ///
+    /// Fails with [CommandError::UnsupportedCommand] without touching the chip if WP# is tied high in
+    /// hardware ([NoPin]), as the status register can not be locked then.
+    pub fn lock_status(&mut self) -> Result<(), CommandError<B, W, H>> {
+        if !W::DRIVEN {
+            return Err(CommandError::UnsupportedCommand);
+        }
+
+        let mut status = self.read_status()?;
+        status.bits_read_only = true;
+
+        self.write_status(status)?;
+        self.set_write_protection(true)
+    }
+
+    /// Unlocks the status register by releasing WP# and clearing BPL afterward.
+    /// WP# is kept deasserted, so the BPL bit has no effect until WP# is asserted again.
+    pub fn unlock_status(&mut self) -> Result<(), CommandError<B, W, H>> {
+        self.set_write_protection(false)?;
+
+        let mut status = self.read_status()?;
+        status.bits_read_only = false;
+
+        self.write_status(status)
+    }
+
+    /// Returns the effective lock state of the status register
+    pub fn lock_state(&mut self) -> Result<LockState, CommandError<B, W, H>> {
+        let status = self.read_status()?;
+
+        Ok(match (status.bits_read_only, self.write_protection) {
+            (false, _) => LockState::Unlocked,
+            (true, false) => LockState::Armed,
+            (true, true) => LockState::Locked,
+        })
+    }
+
+    /// Returns true if all bytes of the given range are erased (0xFF) (s. [Flash::find_first_non_blank])
+    pub fn is_erased(&mut self, range: Range<u32>) -> Result<bool, CommandError<B, W, H>> {
+        Ok(self.find_first_non_blank(range)?.is_none())
+    }
+
+    /// Returns the address of the first byte of the given range not being erased (0xFF), None if
+    /// the whole range is blank. The range is read in chunks of [READ_CHUNK_SIZE] bytes.
+    pub fn find_first_non_blank(&mut self, range: Range<u32>) -> Result<Option<u32>, CommandError<B, W, H>> {
+        if range.is_empty() {
+            return Ok(None);
+        }
+
+        assert_valid_range(self.variant, range.start, range.len())?;
+
+        let mut buffer = [0x0; READ_CHUNK_SIZE];
+        let mut address = range.start;
+
+        while address < range.end {
+            let chunk = &mut buffer[..READ_CHUNK_SIZE.min((range.end - address) as usize)];
+            self.read_data(address, chunk)?;
+
+            if let Some(offset) = chunk.iter().position(|byte| *byte != 0xff) {
+                return Ok(Some(address + offset as u32));
+            }
+
+            address += chunk.len() as u32;
+        }
+
+        Ok(None)
+    }
+
+    /// Compares the memory starting at the given address with the given data in chunks of
+    /// [READ_CHUNK_SIZE] bytes. Returns [CommandError::VerifyFailed] with the address of the first
+    /// mismatching byte.
+    pub fn verify(&mut self, address: u32, data: &[u8]) -> Result<(), CommandError<B, W, H>> {
+        assert_valid_range(self.variant, address, data.len())?;
+
+        let mut buffer = [0x0; READ_CHUNK_SIZE];
+
+        for (index, expected) in data.chunks(READ_CHUNK_SIZE).enumerate() {
+            let chunk_address = address + (index * READ_CHUNK_SIZE) as u32;
+            let actual = &mut buffer[..expected.len()];
+            self.read_data(chunk_address, actual)?;
+
+            if let Some(offset) =
+                actual.iter().zip(expected).position(|(actual, expected)| actual != expected)
+            {
+                return Err(CommandError::VerifyFailed {
+                    address: chunk_address + offset as u32,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Programs the given byte in blocking mode and verifies it afterward (s. [Flash::verify])
+    pub fn byte_program_verified(&mut self, address: u32, data: u8) -> Result<(), CommandError<B, W, H>> {
+        self.with_blocking(true, |flash| flash.byte_program(address, data))?;
+        self.verify(address, &[data])
+    }
+
+    /// AAI programming in blocking mode, verifying the buffer afterward (s. [Flash::verify])
+    pub fn aai_program_verified(&mut self, address: u32, buffer: &[u8]) -> Result<(), CommandError<B, W, H>> {
+        self.with_blocking(true, |flash| flash.aai_program(address, buffer))?;
+        self.verify(address, buffer)
+    }
+
+    /// Writes data of any length in blocking mode, verifying it afterward (s. [Flash::verify])
+    pub fn write_verified(&mut self, address: u32, data: &[u8]) -> Result<(), CommandError<B, W, H>> {
+        self.with_blocking(true, |flash| Memory::write(flash, address, data))?;
+        self.verify(address, data)
+    }
+
+    /// Creates a resumable reader of the given range, which reads one chunk per call (s. [Reader])
+    pub fn reader(&self, address: u32, length: usize) -> Result<Reader, CommandError<B, W, H>> {
+        assert_valid_range(self.variant, address, length)?;
+        Ok(Reader {
+            address,
+            length,
+            offset: 0,
+        })
+    }
+
+    /// Creates a resumable AAI writer for programming the given buffer starting at the given address.
+    /// Buffer needs to contain at least two bytes and an even data amount (s. [AaiWriter]).
+    pub fn aai_writer<'a>(
+        &self,
+        address: u32,
+        buffer: &'a [u8],
+    ) -> Result<AaiWriter<'a>, CommandError<B, W, H>> {
+        assert_aai_buffer(self.variant, address, buffer)?;
+        Ok(AaiWriter::new(address, buffer))
+    }
+
+    /// Erases the range from start (inclusive) to end (exclusive) using the minimal sequence of
+    /// erase operations. Both bounds need to be sector aligned.
+    /// In non-blocking mode, waits between the single steps and returns when the last command is sent.
+    pub fn erase_range(&mut self, start: u32, end: u32) -> Result<(), CommandError<B, W, H>> {
+        let mut plan = self.plan_erase(start, end)?.peekable();
+
+        while let Some(step) = plan.next() {
+            self.erase(step)?;
+
+            if !self.blocking && plan.peek().is_some() {
+                self.wait(true, step.into())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Executes the given function in the given mode and restores the previous mode afterward
+    pub(crate) fn with_blocking<T>(&mut self, blocking: bool, f: impl FnOnce(&mut Self) -> T) -> T {
+        let previous = self.blocking;
+        self.blocking = blocking;
+
+        let result = f(self);
+        self.blocking = previous;
+
+        result
+    }
+
+    /// Reads memory starting at the given address into the given buffer using the configured read mode
+    fn read_data(&mut self, address: u32, buffer: &mut [u8]) -> Result<(), CommandError<B, W, H>> {
+        let mut frame = [0x0; 5];
+        let frame = read_frame(self.variant, self.read_mode, address, buffer.len(), &mut frame)?;
+
+        self.command_response(frame, buffer)
+    }
+
+    /// Auto address increment (AAI) programming using hardware end-of-write detection.
+    /// Buffer needs to contain at least two bytes and an even data amount.
+    ///
+    /// Hardware end-of-write detection is enabled (EBSY) before programming, so that the chip signals
+    /// the completion of each word on the SO line, which is sampled by the [busy line](BusyLine) of
+    /// the SPI device instead of polling the status register. Detection is disabled (DBSY) afterward,
+    /// also in case of errors.
+    ///
+    /// Falls back to software polling (s. [Memory::aai_program]) if the variant does not support
+    /// hardware end-of-write detection.
+    pub fn aai_program_with_busy_line(
+        &mut self,
+        address: u32,
+        buffer: &[u8],
+    ) -> Result<(), CommandError<B, W, H>>
+    where
+        B: BusyLine,
+    {
+        if !self.variant.commands().end_of_write_detection {
+            return self.aai_program(address, buffer);
+        }
+
+        assert_aai_buffer(self.variant, address, buffer)?;
+        self.command(&[CMD_ENABLE_SO_BUSY])?;
+
+        let result = self.aai_words(address, buffer, |flash| {
+            flash.wait_while(WaitFor::Program, |flash| {
+                flash.bus.is_busy().map_err(CommandError::BusyLineError)
+            })
+        });
+
+        // Exiting AAI mode and disabling end-of-write detection, even in case of an error
+        let exit_result = self.write_disable();
+        let disable_result = self.command(&[CMD_DISABLE_SO_BUSY]);
+
+        result.and(exit_result).and(disable_result)
+    }
+
+    /// Writes the bytes outside of the AAI part by Byte-Program first, followed by the AAI part.
+    /// AAI mode is not exited.
+    fn write_segments(&mut self, address: u32, data: &[u8]) -> Result<(), CommandError<B, W, H>> {
+        let aai = aai_part(self.variant, address, data.len());
+
+        for (index, byte) in data.iter().enumerate() {
+            if !aai.contains(&index) {
+                self.byte_program(address + index as u32, *byte)?;
+            }
+        }
+
+        if aai.is_empty() {
+            return Ok(());
+        }
+
+        self.aai_words(address + aai.start as u32, &data[aai], |flash| {
+            flash.wait(true, WaitFor::Program)
+        })
+    }
+
+    /// Enables writing and programs the given buffer word by word in AAI mode.
+    /// Calls the given wait function after each word. AAI mode is not exited.
+    fn aai_words<F>(&mut self, address: u32, buffer: &[u8], mut wait: F) -> Result<(), CommandError<B, W, H>>
+    where
+        F: FnMut(&mut Self) -> Result<(), CommandError<B, W, H>>,
+    {
+        self.write_enable()?;
+        self.assert_not_busy()?;
+
+        let mut frame = [CMD_AAI_PROGRAM, 0x0, 0x0, 0x0, buffer[0], buffer[1]];
+        address_command(address, &mut frame);
+        self.command(&frame)?;
+        wait(self)?;
+
+        for chunk in buffer[2..].chunks(2) {
+            self.command(&[CMD_AAI_PROGRAM, chunk[0], chunk[1]])?;
+            wait(self)?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends the given block erase command frame
+    fn erase_block(&mut self, frame: &[u8]) -> Result<(), CommandError<B, W, H>> {
+        self.write_enable()?;
+        self.assert_not_busy()?;
+
+        self.command(frame)?;
+        self.wait(false, WaitFor::BlockErase)
+    }
+
+    /// Sends the given command frame within a single transaction
+    pub(crate) fn command(&mut self, frame: &[u8]) -> Result<(), CommandError<B, W, H>> {
+        self.assert_accepted(frame[0])?;
+        self.configure()?;
+
+        // The chip state is unknown in case of a transfer error, so AAI mode is assumed
+        if frame[0] == CMD_AAI_PROGRAM {
+            self.aai_active = true;
+        }
+
+        self.bus
+            .transaction(&mut [Operation::Write(frame)])
+            .map_err(CommandError::TransferError)?;
+
+        if frame[0] == CMD_WRITE_DISABLE {
+            self.aai_active = false;
+        }
+
+        Ok(())
+    }
+
+    /// Sends the given command frame and reads the response within a single transaction, so that
+    /// chip select is not released between command and data phase
+    fn command_response(&mut self, frame: &[u8], response: &mut [u8]) -> Result<(), CommandError<B, W, H>> {
+        self.assert_accepted(frame[0])?;
+        self.configure()?;
+        self.bus
+            .transaction(&mut [Operation::Write(frame), Operation::Read(response)])
+            .map_err(CommandError::TransferError)
+    }
+
+    /// Waits until the chip is idle and disables writing, which also exits AAI mode
+    fn restore_safe_state(&mut self) -> Result<(), CommandError<B, W, H>> {
+        self.configure()?;
+        self.wait(true, WaitFor::ChipErase)?;
+        self.write_disable()
+    }
+
+    /// Returns a busy error in case the chip is in AAI mode and ignores the given command
+    fn assert_accepted(&self, command: u8) -> Result<(), CommandError<B, W, H>> {
+        if self.aai_active && ![CMD_READ_STATUS, CMD_WRITE_DISABLE, CMD_AAI_PROGRAM].contains(&command) {
             return Err(CommandError::Busy);
         }
 
         Ok(())
     }
 
-    /// Returns an error if the given address is out of range
-    fn assert_valid_address(&self, address: u32) -> Result<(), CommandError<B, P>> {
-        if address > 16777216 {
-            return Err(CommandError::InvalidAddress);
+    /// Returns an error in case device is busy
+    fn assert_not_busy(&mut self) -> Result<(), CommandError<B, W, H>> {
+        if self.read_status()?.busy {
+            return Err(CommandError::Busy);
         }
 
         Ok(())
     }
 
     /// Blocks until device is not busy anymore
-    fn wait(&mut self, force: bool) -> Result<(), CommandError<B, P>> {
-        while (self.blocking || force) && self.read_status()?.busy {}
+    fn wait(&mut self, force: bool, operation: WaitFor) -> Result<(), CommandError<B, W, H>> {
+        if !self.blocking && !force {
+            return Ok(());
+        }
+
+        self.wait_while(operation, |flash| Ok(flash.read_status()?.busy))
+    }
+
+    /// Blocks while the given function returns true. Returns a timeout error once the timeout of the
+    /// given operation expired, if timeouts are enabled.
+    fn wait_while<F>(&mut self, operation: WaitFor, mut busy: F) -> Result<(), CommandError<B, W, H>>
+    where
+        F: FnMut(&mut Self) -> Result<bool, CommandError<B, W, H>>,
+    {
+        let timeouts = match self.timeouts {
+            Some(timeouts) => timeouts,
+            None => {
+                while busy(self)? {}
+                return Ok(());
+            }
+        };
+
+        let timeout = operation.timeout(&timeouts);
+        let mut elapsed: u32 = 0;
+
+        while busy(self)? {
+            if elapsed >= timeout {
+                return Err(CommandError::Timeout);
+            }
+
+            self.delay.delay_us(timeouts.poll_interval_us);
+            elapsed = elapsed.saturating_add(timeouts.poll_interval_us);
+        }
+
         Ok(())
     }
 
     /// Sets the base GPIO states once
-    fn configure(&mut self) -> Result<(), CommandError<B, P>> {
+    fn configure(&mut self) -> Result<(), CommandError<B, W, H>> {
         if self.configured {
             return Ok(());
         }
@@ -469,6 +1744,151 @@ where
     }
 }
 
+/// Adds the given memory address to the command frame
+pub(crate) fn address_command(address: u32, frame: &mut [u8]) {
+    frame[1] = (address >> 16) as u8;
+    frame[2] = (address >> 8) as u8;
+    frame[3] = address as u8;
+}
+
+/// Returns an error if the given address or the end of the given data length is out of range
+pub(crate) fn assert_valid_range<B: spi::ErrorType, W: digital::ErrorType, H: digital::ErrorType>(
+    variant: Variant,
+    address: u32,
+    length: usize,
+) -> Result<(), CommandError<B, W, H>> {
+    let capacity = variant.capacity() as u64;
+
+    if address as u64 >= capacity || length as u64 > capacity - address as u64 {
+        return Err(CommandError::InvalidAddress);
+    }
+
+    Ok(())
+}
+
+/// Returns an error if the given buffer is not suitable for AAI programming at the given address
+pub(crate) fn assert_aai_buffer<B: spi::ErrorType, W: digital::ErrorType, H: digital::ErrorType>(
+    variant: Variant,
+    address: u32,
+    buffer: &[u8],
+) -> Result<(), CommandError<B, W, H>> {
+    assert_valid_range(variant, address, buffer.len())?;
+
+    if !variant.commands().aai_word_program {
+        return Err(CommandError::UnsupportedCommand);
+    }
+
+    if buffer.len() < 2 {
+        return Err(CommandError::BufferTooSmall);
+    }
+
+    if buffer.len() & 1 == 1 {
+        return Err(CommandError::BufferUneven);
+    }
+
+    Ok(())
+}
+
+/// Returns the indices of the data written by AAI programming, which requires an even start address
+/// and an even length. The remaining leading and trailing bytes are written by Byte-Program.
+pub(crate) fn aai_part(variant: Variant, address: u32, length: usize) -> Range<usize> {
+    if !variant.commands().aai_word_program {
+        return 0..0;
+    }
+
+    let start = (address & 1) as usize;
+    let length = length.saturating_sub(start) & !1;
+
+    start..start + length
+}
+
+/// Builds the frame of the given 32 KByte or 64 KByte block erase command
+pub(crate) fn block_erase_frame<B: spi::ErrorType, W: digital::ErrorType, H: digital::ErrorType>(
+    variant: Variant,
+    command: u8,
+    address: u32,
+) -> Result<[u8; 4], CommandError<B, W, H>> {
+    let (supported, size) = match command {
+        CMD_BLOCK_ERASE_32K => (variant.commands().block_erase_32k, BLOCK_32K_SIZE),
+        _ => (variant.commands().block_erase_64k, BLOCK_64K_SIZE),
+    };
+
+    if !supported {
+        return Err(CommandError::UnsupportedCommand);
+    }
+
+    assert_valid_range(variant, address, 1)?;
+
+    if !address.is_multiple_of(size) {
+        return Err(CommandError::UnalignedAddress);
+    }
+
+    let mut frame = [command, 0x0, 0x0, 0x0];
+    address_command(address, &mut frame);
+    Ok(frame)
+}
+
+/// Builds the read command frame for the given read mode. Returns the used part of the given buffer.
+pub(crate) fn read_frame<B: spi::ErrorType, W: digital::ErrorType, H: digital::ErrorType>(
+    variant: Variant,
+    mode: ReadMode,
+    address: u32,
+    length: usize,
+    frame: &mut [u8; 5],
+) -> Result<&[u8], CommandError<B, W, H>> {
+    assert_valid_range(variant, address, length)?;
+
+    let frame = match mode {
+        ReadMode::Normal => {
+            frame[0] = CMD_READ;
+            &mut frame[..4]
+        }
+        ReadMode::HighSpeed => {
+            if !variant.commands().high_speed_read {
+                return Err(CommandError::UnsupportedCommand);
+            }
+
+            // Last byte is the dummy byte
+            frame[0] = CMD_HIGH_SPEED_READ;
+            &mut frame[..]
+        }
+    };
+
+    address_command(address, frame);
+    Ok(frame)
+}
+
+/// Plans the erase operations for the given range after validating the bounds
+pub(crate) fn plan_erase<B: spi::ErrorType, W: digital::ErrorType, H: digital::ErrorType>(
+    variant: Variant,
+    start: u32,
+    end: u32,
+) -> Result<ErasePlan, CommandError<B, W, H>> {
+    let capacity = variant.capacity();
+
+    if start > end || end > capacity {
+        return Err(CommandError::InvalidAddress);
+    }
+
+    if !start.is_multiple_of(SECTOR_SIZE) || !end.is_multiple_of(SECTOR_SIZE) {
+        return Err(CommandError::UnalignedAddress);
+    }
+
+    Ok(ErasePlan::new(start, end, capacity, variant.commands()))
+}
+
+/// Maps the legacy device ID to a known variant, used if the JEDEC ID is unknown
+pub(crate) fn identify_legacy<B: spi::ErrorType, W: digital::ErrorType, H: digital::ErrorType>(
+    jedec_id: ChipId,
+    device_id: DeviceId,
+) -> Result<Variant, CommandError<B, W, H>> {
+    match device_id.variant() {
+        Some(variant) => Ok(variant),
+        None if jedec_id.is_blank() && device_id.is_blank() => Err(CommandError::NoChip),
+        None => Err(CommandError::UnknownChip(jedec_id)),
+    }
+}
+
 /// Mapped status register
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Status {
@@ -539,21 +1959,48 @@ impl Status {
 
         result
     }
+
+    /// Returns the block-protection bits BP3..BP0 as combined value
+    pub fn block_protection(&self) -> u8 {
+        (self.block0_protected as u8)
+            | (self.block1_protected as u8) << 1
+            | (self.block2_protected as u8) << 2
+            | (self.block3_protected as u8) << 3
+    }
+
+    /// Sets the block-protection bits BP3..BP0 from the given combined value
+    pub fn set_block_protection(&mut self, bits: u8) {
+        self.block0_protected = bits & (1 << 0) != 0;
+        self.block1_protected = bits & (1 << 1) != 0;
+        self.block2_protected = bits & (1 << 2) != 0;
+        self.block3_protected = bits & (1 << 3) != 0;
+    }
 }
 
-impl<B: SpiDevice<u8>, P: OutputPin> Debug for CommandError<B, P>
+impl<B: spi::ErrorType, W: digital::ErrorType, H: digital::ErrorType> Debug for CommandError<B, W, H>
 where
-    P::Error: Debug,
+    W::Error: Debug,
+    H::Error: Debug,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         match self {
-            CommandError::TransferError(error) => write!(f, "StatusWriteError{error:?}"),
-            CommandError::HoldPinError(error) => write!(f, "StatusWriteError{error:?}"),
-            CommandError::WriteProtectionPinError(error) => write!(f, "WriteProtectionPinError{error:?}"),
+            CommandError::TransferError(error) => write!(f, "TransferError({error:?})"),
+            CommandError::HoldPinError(error) => write!(f, "HoldPinError({error:?})"),
+            CommandError::WriteProtectionPinError(error) => write!(f, "WriteProtectionPinError({error:?})"),
+            CommandError::BusyLineError(error) => write!(f, "BusyLineError({error:?})"),
             CommandError::Busy => f.write_str("Busy"),
             CommandError::InvalidAddress => f.write_str("InvalidAddress"),
             CommandError::BufferTooSmall => f.write_str("BufferTooSmall"),
             CommandError::BufferUneven => f.write_str("BufferUneven"),
+            CommandError::UnalignedAddress => f.write_str("UnalignedAddress"),
+            CommandError::UnsupportedCommand => f.write_str("UnsupportedCommand"),
+            CommandError::StatusWriteFailed => f.write_str("StatusWriteFailed"),
+            CommandError::StatusLocked => f.write_str("StatusLocked"),
+            CommandError::NoChip => f.write_str("NoChip"),
+            CommandError::UnknownChip(id) => write!(f, "UnknownChip({id:?})"),
+            CommandError::Timeout => f.write_str("Timeout"),
+            CommandError::UnsupportedRange => f.write_str("UnsupportedRange"),
+            CommandError::VerifyFailed { address } => write!(f, "VerifyFailed({address:#x})"),
         }
     }
 }
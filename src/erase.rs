@@ -0,0 +1,106 @@
+//! # Erase planning
+//!
+//! Plans the minimal sequence of full-chip, 64 KByte block, 32 KByte block and 4 KByte sector erase
+//! operations covering exactly a sector aligned memory range.
+//!
+//! The plan is created by [Flash::plan_erase](crate::device::Flash::plan_erase) and may be executed
+//! step by step using [Flash::erase](crate::device::Flash::erase), e.g. in non-blocking mode:
+//!
+//! ````
+//!# use mc_sst25::device::{Flash, Memory};
+//!# use mc_sst25::erase::EraseStep;
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//! let mut device = Flash::new(bus, pin_wp, pin_hold);
+//! device.set_non_blocking();
+//!
+//! let mut plan = device.plan_erase(0x7000, 0x20000).unwrap();
+//! assert_eq!(Some(EraseStep::Sector(0x7000)), plan.next());
+//! assert_eq!(Some(EraseStep::Block32K(0x8000)), plan.next());
+//! assert_eq!(Some(EraseStep::Block64K(0x10000)), plan.next());
+//! assert_eq!(None, plan.next());
+//!
+//! for step in device.plan_erase(0x7000, 0x20000).unwrap() {
+//!     // Waiting until the previous step is completed
+//!     while device.read_status().unwrap().busy {}
+//!
+//!     device.erase(step).unwrap();
+//! }
+//! ````
+use crate::chip::{Commands, BLOCK_32K_SIZE, BLOCK_64K_SIZE, SECTOR_SIZE};
+
+/// Single erase operation
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EraseStep {
+    /// Erases the full chip
+    Full,
+
+    /// Erases the 64 KByte block starting at the given address
+    Block64K(u32),
+
+    /// Erases the 32 KByte block starting at the given address
+    Block32K(u32),
+
+    /// Erases the 4 KByte sector starting at the given address
+    Sector(u32),
+}
+
+/// Iterator over the erase operations covering a memory range
+#[derive(Clone, Debug)]
+pub struct ErasePlan {
+    /// Start address of the next step
+    next: u32,
+
+    /// End address (exclusive) of the range
+    end: u32,
+
+    /// Capacity of the chip in bytes
+    capacity: u32,
+
+    /// Commands supported by the chip
+    commands: Commands,
+}
+
+impl ErasePlan {
+    /// Creates a new plan for the given range. Bounds are expected to be validated already.
+    pub(crate) fn new(start: u32, end: u32, capacity: u32, commands: Commands) -> Self {
+        Self {
+            next: start,
+            end,
+            capacity,
+            commands,
+        }
+    }
+
+    /// True if a block of the given size starts at the next address and fits into the remaining range
+    fn fits(&self, size: u32) -> bool {
+        self.next.is_multiple_of(size) && self.end - self.next >= size
+    }
+}
+
+impl Iterator for ErasePlan {
+    type Item = EraseStep;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.end {
+            return None;
+        }
+
+        let (step, size) = if self.next == 0 && self.end == self.capacity {
+            (EraseStep::Full, self.capacity)
+        } else if self.commands.block_erase_64k && self.fits(BLOCK_64K_SIZE) {
+            (EraseStep::Block64K(self.next), BLOCK_64K_SIZE)
+        } else if self.commands.block_erase_32k && self.fits(BLOCK_32K_SIZE) {
+            (EraseStep::Block32K(self.next), BLOCK_32K_SIZE)
+        } else {
+            (EraseStep::Sector(self.next), SECTOR_SIZE)
+        };
+
+        self.next += size;
+        Some(step)
+    }
+}
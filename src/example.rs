@@ -1,9 +1,9 @@
 //! # Mocks for doc examples
 use core::convert::Infallible;
-use embedded_hal::digital::{ErrorType, OutputPin};
-use embedded_hal::spi::{Operation, SpiDevice};
+use embedded_hal::digital::{ErrorType, InputPin, OutputPin};
+use embedded_hal::spi::{Operation, SpiBus, SpiDevice};
 
-/// Mocked GPIO output pin
+/// Mocked GPIO pin, always reading high
 #[derive(Default, Debug)]
 pub struct MockPin {}
 
@@ -21,11 +21,24 @@ impl OutputPin for MockPin {
     }
 }
 
+impl InputPin for MockPin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+}
+
 /// Mocked SPI Bus
 #[derive(Default, Debug)]
 pub struct MockBus {
-    /// Was previous transfer a read command?
-    read_command: bool,
+    /// Command byte of the previous write operation
+    command: Option<u8>,
+
+    /// Last written status register
+    status: u8,
 }
 
 impl embedded_hal::spi::ErrorType for MockBus {
@@ -36,21 +49,30 @@ impl SpiDevice<u8> for MockBus {
     fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
         for operation in operations {
             match operation {
-                Operation::Read(buffer) => {
-                    if self.read_command {
-                        self.read_command = false;
-
-                        if buffer.len() == 5 {
-                            buffer.copy_from_slice(&[0x66, 0x1, 0x2, 0x3, 0x4])
-                        } else {
-                            buffer.copy_from_slice(&[0xa, 0xb, 0xc, 0xd])
+                Operation::Read(buffer) => match self.command.take() {
+                    // Read & High-Speed-Read
+                    Some(0b0000_0011 | 0b0000_1011) => {
+                        match buffer.len() {
+                            4 => buffer.copy_from_slice(&[0xa, 0xb, 0xc, 0xd]),
+                            5 => buffer.copy_from_slice(&[0x66, 0x1, 0x2, 0x3, 0x4]),
+                            _ => buffer.fill(0xff),
                         };
                     }
-                }
+                    // Read-Status-Register
+                    Some(0b0000_0101) => buffer[0] = self.status,
+                    // JEDEC-ID of SST25VF080B
+                    Some(0b1001_1111) => buffer.copy_from_slice(&[0xbf, 0x25, 0x8e]),
+                    // Read-ID of SST25VF080B
+                    Some(0b1001_0000) => buffer.copy_from_slice(&[0xbf, 0x8e]),
+                    _ => {}
+                },
                 Operation::Write(words) => {
-                    if words[0] == 0b0000_0011 {
-                        self.read_command = true;
+                    // Write-Status-Register
+                    if words[0] == 0b0000_0001 {
+                        self.status = words[1];
                     }
+
+                    self.command = Some(words[0]);
                 }
                 Operation::Transfer(_, _) => unimplemented!(),
                 Operation::TransferInPlace(_) => unimplemented!(),
@@ -61,3 +83,45 @@ impl SpiDevice<u8> for MockBus {
         Ok(())
     }
 }
+
+impl SpiBus<u8> for MockBus {
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        SpiDevice::transaction(self, &mut [Operation::Read(words)])
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        SpiDevice::transaction(self, &mut [Operation::Write(words)])
+    }
+
+    fn transfer(&mut self, _read: &mut [u8], _write: &[u8]) -> Result<(), Self::Error> {
+        unimplemented!()
+    }
+
+    fn transfer_in_place(&mut self, _words: &mut [u8]) -> Result<(), Self::Error> {
+        unimplemented!()
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::spi::SpiDevice<u8> for MockBus {
+    async fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        SpiDevice::transaction(self, operations)
+    }
+}
+
+/// Mocked delay, which returns immediately
+#[derive(Default, Debug)]
+pub struct MockDelay {}
+
+impl embedded_hal::delay::DelayNs for MockDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::delay::DelayNs for MockDelay {
+    async fn delay_ns(&mut self, _ns: u32) {}
+}
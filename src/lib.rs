@@ -5,7 +5,8 @@
 //!
 //! # Example
 //!
-//! For all details see [device] module.
+//! For all details see [device] module. Chip identification is described in [chip] module.
+//! An async driver is available in `async_device` module when enabling the `async` feature.
 //!
 //! ````
 //! use mc_sst25::device::{Flash, Memory};
@@ -31,11 +32,20 @@
 #![cfg_attr(not(test), no_std)]
 #![cfg_attr(feature = "strict", deny(warnings))]
 
+#[cfg(feature = "async")]
+pub mod async_device;
+pub mod bus;
+pub mod chip;
 pub mod device;
+pub mod erase;
+pub mod pending;
+pub mod storage;
 
 #[cfg(feature = "example")]
 pub mod example;
 
+#[cfg(all(test, feature = "async"))]
+mod async_tests;
 #[cfg(test)]
 mod mocks;
 #[cfg(test)]
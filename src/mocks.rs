@@ -1,6 +1,8 @@
+use crate::device::BusyLine;
 use core::fmt::{Debug, Formatter};
-use embedded_hal::digital::OutputPin;
-use embedded_hal::spi::{Operation, SpiDevice};
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal::spi::{Operation, SpiBus, SpiDevice};
 use mockall::mock;
 
 #[derive(Debug, Copy, Clone)]
@@ -19,6 +21,10 @@ mock! {
     impl SpiDevice<u8> for SPIBus{
         fn transaction<'a>(&mut self, operations: &mut [Operation<'a, u8>]) -> Result<(), BusError>;
     }
+
+    impl BusyLine for SPIBus {
+        fn is_busy(&mut self) -> Result<bool, BusError>;
+    }
 }
 
 impl embedded_hal::spi::ErrorType for MockSPIBus {
@@ -38,6 +44,45 @@ impl embedded_hal::digital::ErrorType for MockPin {
     type Error = PinError;
 }
 
+mock! {
+    pub RawBus {}
+
+    impl SpiBus<u8> for RawBus {
+        fn read(&mut self, words: &mut [u8]) -> Result<(), BusError>;
+        fn write(&mut self, words: &[u8]) -> Result<(), BusError>;
+        fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), BusError>;
+        fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), BusError>;
+        fn flush(&mut self) -> Result<(), BusError>;
+    }
+}
+
+impl embedded_hal::spi::ErrorType for MockRawBus {
+    type Error = BusError;
+}
+
+mock! {
+    pub InputPin {}
+
+    impl InputPin for InputPin {
+        fn is_high(&mut self) -> Result<bool, PinError>;
+        fn is_low(&mut self) -> Result<bool, PinError>;
+    }
+}
+
+impl embedded_hal::digital::ErrorType for MockInputPin {
+    type Error = PinError;
+}
+
+mock! {
+    pub Delay {}
+
+    impl DelayNs for Delay {
+        fn delay_ns(&mut self, ns: u32);
+        fn delay_us(&mut self, us: u32);
+        fn delay_ms(&mut self, ms: u32);
+    }
+}
+
 impl Debug for MockSPIBus {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         f.write_str("MockSPIBus")
@@ -61,3 +106,35 @@ impl embedded_hal::spi::Error for BusError {
         embedded_hal::spi::ErrorKind::Other
     }
 }
+
+#[cfg(feature = "async")]
+mock! {
+    pub AsyncSPIBus {}
+
+    impl embedded_hal_async::spi::SpiDevice<u8> for AsyncSPIBus {
+        async fn transaction<'a>(&mut self, operations: &mut [Operation<'a, u8>]) -> Result<(), BusError>;
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal::spi::ErrorType for MockAsyncSPIBus {
+    type Error = BusError;
+}
+
+#[cfg(feature = "async")]
+impl Debug for MockAsyncSPIBus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str("MockAsyncSPIBus")
+    }
+}
+
+#[cfg(feature = "async")]
+mock! {
+    pub AsyncDelay {}
+
+    impl embedded_hal_async::delay::DelayNs for AsyncDelay {
+        async fn delay_ns(&mut self, ns: u32);
+        async fn delay_us(&mut self, us: u32);
+        async fn delay_ms(&mut self, ms: u32);
+    }
+}
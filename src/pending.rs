@@ -0,0 +1,296 @@
+//! # Pending operations
+//!
+//! Erase and program operations may be started without waiting for their completion using the
+//! `start_*` methods of [Flash], e.g.
+//! [start_erase_sector](Flash::start_erase_sector). These return a [Pending] handle,
+//! which is polled until the chip has completed the operation.
+//!
+//! [Pending::poll] follows the [nb] semantics, so it returns [nb::Error::WouldBlock] as long as the
+//! chip is busy:
+//!
+//! ````
+//!# use mc_sst25::device::Flash;
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//! let mut device = Flash::new(bus, pin_wp, pin_hold);
+//!
+//! let mut pending = device.start_erase_sector(0x1000).unwrap();
+//!
+//! loop {
+//!     match pending.poll() {
+//!         Ok(()) => break,
+//!         Err(nb::Error::WouldBlock) => {
+//!             // Doing something else in the meantime
+//!         }
+//!         Err(nb::Error::Other(error)) => panic!("Erase failed: {:?}", error),
+//!     }
+//! }
+//! ````
+//!
+//! The handle borrows the device mutably, so no other command may be issued while the operation is
+//! outstanding:
+//!
+//! ````compile_fail
+//!# use mc_sst25::device::{Flash, Memory};
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//! let mut device = Flash::new(bus, pin_wp, pin_hold);
+//!
+//! let mut pending = device.start_byte_program(0x0, 0x66).unwrap();
+//! device.byte_program(0x1, 0x67).unwrap();
+//! pending.poll().unwrap();
+//! ````
+//!
+//! ## AAI programming
+//!
+//! Larger data is programmed in non-blocking manner by an [AaiWriter], which is created by
+//! [Flash::aai_writer]. Each call of [AaiWriter::poll] programs
+//! the next word, if the chip is idle. The writer does not borrow the device, so programming may be
+//! paused for issuing other commands and resumed at the next address afterward. While the chip is in
+//! AAI mode, any command except reading the status is rejected with
+//! [CommandError::Busy], as the chip would ignore it.
+//!
+//! ````
+//!# use mc_sst25::device::{Flash, Memory};
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//! let mut device = Flash::new(bus, pin_wp, pin_hold);
+//!
+//! let data = [0x1, 0x2, 0x3, 0x4, 0x5, 0x6];
+//! let mut writer = device.aai_writer(0x1000, &data).unwrap();
+//!
+//! // Programming the first two words
+//! while writer.written() < 4 {
+//!     let _ = writer.poll(&mut device);
+//! }
+//!
+//! // Exiting AAI mode for reading memory
+//! nb::block!(writer.pause(&mut device)).unwrap();
+//! let _ = device.read::<4>(0x1000).unwrap();
+//!
+//! // Resuming at address 0x1004
+//! assert_eq!(0x1004, writer.next_address());
+//! nb::block!(writer.poll(&mut device)).unwrap();
+//! assert!(writer.is_completed());
+//! ````
+use crate::device::{address_command, CommandError, ControlPin, Flash, Memory, CMD_AAI_PROGRAM};
+use core::fmt::Debug;
+use core::marker::PhantomData;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::spi::SpiDevice;
+
+/// Erase of a 4 KByte sector
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SectorErase;
+
+/// Erase of a 32 KByte or 64 KByte block
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BlockErase;
+
+/// Erase of the full chip
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ChipErase;
+
+/// Programming of a single byte
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ByteProgram;
+
+/// Handle of an operation started in non-blocking manner. The operation type is given by `Op`.
+pub struct Pending<'a, M: Memory, Op> {
+    /// Device executing the operation
+    device: &'a mut M,
+
+    /// True if the operation has been completed
+    completed: bool,
+
+    /// Type of operation
+    operation: PhantomData<Op>,
+}
+
+impl<'a, M: Memory, Op> Pending<'a, M, Op> {
+    /// Creates a new handle of an operation just started on the given device
+    pub(crate) fn new(device: &'a mut M) -> Self {
+        Self {
+            device,
+            completed: false,
+            operation: PhantomData,
+        }
+    }
+
+    /// Checks the status register. Returns [nb::Error::WouldBlock] while the chip is busy.
+    /// Once completed, Ok is returned without accessing the chip again.
+    pub fn poll(&mut self) -> nb::Result<(), M::Error> {
+        if self.completed {
+            return Ok(());
+        }
+
+        if self.device.read_status().map_err(nb::Error::Other)?.busy {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        self.completed = true;
+        Ok(())
+    }
+
+    /// Blocks until the operation is completed
+    pub fn wait(mut self) -> Result<(), M::Error> {
+        nb::block!(self.poll())
+    }
+
+    /// True if the operation has been completed by a previous poll
+    pub fn is_completed(&self) -> bool {
+        self.completed
+    }
+}
+
+/// Resumable AAI programming, which programs one word per poll (s. [module](self) docs)
+#[derive(Clone, Debug)]
+pub struct AaiWriter<'a> {
+    /// Start address
+    address: u32,
+
+    /// Data to program
+    data: &'a [u8],
+
+    /// Number of bytes already programmed
+    written: usize,
+
+    /// True if the chip is in AAI mode
+    active: bool,
+}
+
+impl<'a> AaiWriter<'a> {
+    /// Creates a new writer. Buffer is expected to be validated already.
+    pub(crate) fn new(address: u32, data: &'a [u8]) -> Self {
+        Self {
+            address,
+            data,
+            written: 0,
+            active: false,
+        }
+    }
+
+    /// Programs the next word if the chip is idle. Returns [nb::Error::WouldBlock] until all words
+    /// are programmed and AAI mode is exited.
+    ///
+    /// In case of an error, AAI mode is exited, so that programming may be resumed by polling again.
+    pub fn poll<B: SpiDevice<u8>, W: ControlPin, H: ControlPin, D: DelayNs>(
+        &mut self,
+        flash: &mut Flash<B, W, H, D>,
+    ) -> nb::Result<(), CommandError<B, W, H>>
+    where
+        W::Error: Debug,
+        H::Error: Debug,
+    {
+        if self.is_completed() {
+            return Ok(());
+        }
+
+        let result = self.step(flash);
+
+        if matches!(result, Err(nb::Error::Other(_))) && self.active {
+            // Recovering by exiting AAI mode, the original error is more relevant
+            let _ = flash.write_disable();
+            self.active = false;
+        }
+
+        result
+    }
+
+    /// Exits AAI mode once the chip is idle. Programming is resumed at the next address by polling again.
+    pub fn pause<B: SpiDevice<u8>, W: ControlPin, H: ControlPin, D: DelayNs>(
+        &mut self,
+        flash: &mut Flash<B, W, H, D>,
+    ) -> nb::Result<(), CommandError<B, W, H>>
+    where
+        W::Error: Debug,
+        H::Error: Debug,
+    {
+        if !self.active {
+            return Ok(());
+        }
+
+        if flash.read_status()?.busy {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        flash.write_disable()?;
+        self.active = false;
+
+        Ok(())
+    }
+
+    /// Returns the number of bytes already programmed
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Returns the number of bytes left for programming
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.written
+    }
+
+    /// Returns the address of the next word to program
+    pub fn next_address(&self) -> u32 {
+        self.address + self.written as u32
+    }
+
+    /// True if the chip is in AAI mode, i.e. programming is neither paused nor completed
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// True if all words are programmed and AAI mode is exited
+    pub fn is_completed(&self) -> bool {
+        self.remaining() == 0 && !self.active
+    }
+
+    /// Executes the next programming step if the chip is idle
+    fn step<B: SpiDevice<u8>, W: ControlPin, H: ControlPin, D: DelayNs>(
+        &mut self,
+        flash: &mut Flash<B, W, H, D>,
+    ) -> nb::Result<(), CommandError<B, W, H>>
+    where
+        W::Error: Debug,
+        H::Error: Debug,
+    {
+        if flash.read_status()?.busy {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        if self.remaining() == 0 {
+            flash.write_disable()?;
+            self.active = false;
+            return Ok(());
+        }
+
+        let word = &self.data[self.written..self.written + 2];
+
+        if self.active {
+            flash.command(&[CMD_AAI_PROGRAM, word[0], word[1]])?;
+        } else {
+            flash.write_enable()?;
+
+            let mut frame = [CMD_AAI_PROGRAM, 0x0, 0x0, 0x0, word[0], word[1]];
+            address_command(self.next_address(), &mut frame);
+
+            self.active = true;
+            flash.command(&frame)?;
+        }
+
+        self.written += 2;
+        Err(nb::Error::WouldBlock)
+    }
+}
@@ -0,0 +1,338 @@
+//! # embedded-storage integration
+//!
+//! [Flash] implements the [NorFlash] and [ReadNorFlash] traits of
+//! [embedded-storage](embedded_storage), so the chip may be used by storage crates of the ecosystem.
+//!
+//! * Reads and writes are possible at any address and length (`READ_SIZE` and `WRITE_SIZE` of one byte)
+//! * Erasing requires sector aligned bounds (`ERASE_SIZE` of 4 KByte). The range is erased using the
+//!   minimal sequence of erase operations (s. [erase planning](crate::erase)).
+//!
+//! Both erase and write operations return when the chip has completed the operation, also if the device
+//! is in non-blocking mode.
+//!
+//! As [Memory] and [Flash] define methods of the same name (e.g. `read` and `erase`), the trait methods
+//! are called in qualified form:
+//!
+//! ````
+//!# use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+//!# use mc_sst25::device::Flash;
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//! let mut device = Flash::new(bus, pin_wp, pin_hold);
+//!
+//! NorFlash::erase(&mut device, 0x1000, 0x3000).unwrap();
+//! NorFlash::write(&mut device, 0x1001, &[0x1, 0x2, 0x3, 0x4]).unwrap();
+//!
+//! let mut buffer = [0x0; 4];
+//! ReadNorFlash::read(&mut device, 0x1001, &mut buffer).unwrap();
+//! ````
+//!
+//! ## Random-access storage
+//!
+//! [SectorStorage] implements the [Storage] trait, which allows overwriting data at any address like
+//! an EEPROM. Writes requiring a transition of any bit from 0 to 1 are handled by reading the affected
+//! sector into the given scratch buffer, erasing the sector, merging the new data and reprogramming
+//! the sector. Writes spanning multiple sectors are handled sector by sector. Other writes are
+//! programmed directly without erasing.
+//!
+//! ````
+//!# use embedded_storage::{ReadStorage, Storage};
+//!# use mc_sst25::chip::SECTOR_SIZE;
+//!# use mc_sst25::device::Flash;
+//!# use mc_sst25::example::{MockBus, MockPin};
+//!# use mc_sst25::storage::SectorStorage;
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!#
+//! let device = Flash::new(bus, pin_wp, pin_hold);
+//! let mut buffer = [0x0; SECTOR_SIZE as usize];
+//! let mut storage = SectorStorage::new(device, &mut buffer);
+//!
+//! storage.write(0xfff, &[0x1, 0x2, 0x3]).unwrap();
+//!
+//! let mut data = [0x0; 4];
+//! storage.read(0x1000, &mut data).unwrap();
+//! ````
+//!
+//! ## Async
+//!
+//! With the `async` feature enabled, `AsyncFlash` implements the
+//! NorFlash and ReadNorFlash traits of [embedded-storage-async](https://docs.rs/embedded-storage-async)
+//! with the same sizes. Erase and write operations sleep between the status polls instead of spinning.
+//!
+//! ````
+//!# #[cfg(feature = "async")]
+//!# {
+//!# use embedded_storage_async::nor_flash::{NorFlash, ReadNorFlash};
+//!# use mc_sst25::async_device::AsyncFlash;
+//!# use mc_sst25::example::{MockBus, MockDelay, MockPin};
+//!#
+//!# let bus = MockBus::default();
+//!# let pin_hold = MockPin::default();
+//!# let pin_wp = MockPin::default();
+//!# let delay = MockDelay::default();
+//!#
+//!# embassy_futures::block_on(async {
+//! let mut device = AsyncFlash::new(bus, pin_wp, pin_hold, delay);
+//!
+//! NorFlash::erase(&mut device, 0x1000, 0x3000).await.unwrap();
+//! NorFlash::write(&mut device, 0x1001, &[0x1, 0x2, 0x3, 0x4]).await.unwrap();
+//!
+//! let mut buffer = [0x0; 4];
+//! ReadNorFlash::read(&mut device, 0x1001, &mut buffer).await.unwrap();
+//!# });
+//!# }
+//! ````
+#[cfg(feature = "async")]
+use crate::async_device::AsyncFlash;
+use crate::chip::SECTOR_SIZE;
+use crate::device::{assert_valid_range, CommandError, ControlPin, Flash, Memory, NoDelay};
+use core::fmt::Debug;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::spi::SpiDevice;
+use embedded_hal::{digital, spi};
+use embedded_storage::nor_flash::{ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash};
+use embedded_storage::{ReadStorage, Storage};
+
+impl<B: spi::ErrorType, W: digital::ErrorType, H: digital::ErrorType> NorFlashError
+    for CommandError<B, W, H>
+{
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            CommandError::InvalidAddress => NorFlashErrorKind::OutOfBounds,
+            CommandError::UnalignedAddress => NorFlashErrorKind::NotAligned,
+            _ => NorFlashErrorKind::Other,
+        }
+    }
+}
+
+impl<B: SpiDevice<u8>, W: ControlPin, H: ControlPin, D: DelayNs> ErrorType for Flash<B, W, H, D>
+where
+    W::Error: Debug,
+    H::Error: Debug,
+{
+    type Error = CommandError<B, W, H>;
+}
+
+impl<B: SpiDevice<u8>, W: ControlPin, H: ControlPin, D: DelayNs> ReadNorFlash for Flash<B, W, H, D>
+where
+    W::Error: Debug,
+    H::Error: Debug,
+{
+    const READ_SIZE: usize = 1;
+
+    /// Reads data starting at the given address using the configured read mode
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.read_into(offset, bytes)
+    }
+
+    /// Returns the capacity of the configured chip variant
+    fn capacity(&self) -> usize {
+        self.variant().capacity() as usize
+    }
+}
+
+impl<B: SpiDevice<u8>, W: ControlPin, H: ControlPin, D: DelayNs> NorFlash for Flash<B, W, H, D>
+where
+    W::Error: Debug,
+    H::Error: Debug,
+{
+    const WRITE_SIZE: usize = 1;
+
+    const ERASE_SIZE: usize = SECTOR_SIZE as usize;
+
+    /// Erases the range from start (inclusive) to end (exclusive) and waits until completed
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        self.with_blocking(true, |flash| flash.erase_range(from, to))
+    }
+
+    /// Writes the given data at any address and waits until completed (s. [Memory::write])
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        Memory::write(self, offset, bytes)
+    }
+}
+
+/// Random-access storage, which erases and reprograms sectors as needed (s. [module](self) docs)
+pub struct SectorStorage<'a, B: SpiDevice<u8>, W: ControlPin, H: ControlPin = W, D: DelayNs = NoDelay>
+where
+    W::Error: Debug,
+    H::Error: Debug,
+{
+    /// Flash device
+    flash: Flash<B, W, H, D>,
+
+    /// Scratch buffer for merging a sector
+    buffer: &'a mut [u8; SECTOR_SIZE as usize],
+}
+
+impl<'a, B: SpiDevice<u8>, W: ControlPin, H: ControlPin, D: DelayNs> SectorStorage<'a, B, W, H, D>
+where
+    W::Error: Debug,
+    H::Error: Debug,
+{
+    /// Creates a new storage using the given scratch buffer for merging sectors
+    pub fn new(flash: Flash<B, W, H, D>, buffer: &'a mut [u8; SECTOR_SIZE as usize]) -> Self {
+        Self { flash, buffer }
+    }
+
+    /// Returns the flash device
+    pub fn into_inner(self) -> Flash<B, W, H, D> {
+        self.flash
+    }
+
+    /// Writes the given data, which is contained within a single sector
+    fn write_sector(&mut self, address: u32, data: &[u8]) -> Result<(), CommandError<B, W, H>> {
+        let current = &mut self.buffer[..data.len()];
+        self.flash.read_into(address, current)?;
+
+        if current == data {
+            return Ok(());
+        }
+
+        // Programming is only able to clear bits
+        if current.iter().zip(data).all(|(current, new)| current & new == *new) {
+            return Memory::write(&mut self.flash, address, data);
+        }
+
+        let sector = address - address % SECTOR_SIZE;
+        let offset = (address - sector) as usize;
+
+        self.flash.read_into(sector, &mut self.buffer[..])?;
+        self.buffer[offset..offset + data.len()].copy_from_slice(data);
+
+        self.flash.with_blocking(true, |flash| flash.erase_sector(sector))?;
+        self.program_sector(sector)
+    }
+
+    /// Programs the erased sector with the buffer content. Bytes in erased state (0xFF) are skipped.
+    fn program_sector(&mut self, sector: u32) -> Result<(), CommandError<B, W, H>> {
+        let mut offset = 0;
+
+        while let Some(start) = self.buffer[offset..].iter().position(|byte| *byte != 0xff) {
+            let start = offset + start;
+            let end = match self.buffer[start..].iter().position(|byte| *byte == 0xff) {
+                Some(length) => start + length,
+                None => self.buffer.len(),
+            };
+
+            Memory::write(&mut self.flash, sector + start as u32, &self.buffer[start..end])?;
+            offset = end;
+        }
+
+        Ok(())
+    }
+}
+
+impl<B: SpiDevice<u8>, W: ControlPin, H: ControlPin, D: DelayNs> ReadStorage for SectorStorage<'_, B, W, H, D>
+where
+    W::Error: Debug,
+    H::Error: Debug,
+{
+    type Error = CommandError<B, W, H>;
+
+    /// Reads data starting at the given address using the configured read mode
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.flash.read_into(offset, bytes)
+    }
+
+    /// Returns the capacity of the configured chip variant
+    fn capacity(&self) -> usize {
+        self.flash.variant().capacity() as usize
+    }
+}
+
+impl<B: SpiDevice<u8>, W: ControlPin, H: ControlPin, D: DelayNs> Storage for SectorStorage<'_, B, W, H, D>
+where
+    W::Error: Debug,
+    H::Error: Debug,
+{
+    /// Writes the given data at any address. Affected sectors are erased and reprogrammed if
+    /// the data requires any bit to change from 0 to 1.
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        assert_valid_range(self.flash.variant(), offset, bytes.len())?;
+
+        let mut address = offset;
+        let mut data = bytes;
+
+        while !data.is_empty() {
+            let length = data.len().min((SECTOR_SIZE - address % SECTOR_SIZE) as usize);
+            self.write_sector(address, &data[..length])?;
+
+            address += length as u32;
+            data = &data[length..];
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<B, W, H, D> ErrorType for AsyncFlash<B, W, H, D>
+where
+    B: embedded_hal_async::spi::SpiDevice<u8>,
+    W: ControlPin,
+    H: ControlPin,
+    W::Error: Debug,
+    H::Error: Debug,
+    D: embedded_hal_async::delay::DelayNs,
+{
+    type Error = CommandError<B, W, H>;
+}
+
+#[cfg(feature = "async")]
+impl<B, W, H, D> embedded_storage_async::nor_flash::ReadNorFlash for AsyncFlash<B, W, H, D>
+where
+    B: embedded_hal_async::spi::SpiDevice<u8>,
+    W: ControlPin,
+    H: ControlPin,
+    W::Error: Debug,
+    H::Error: Debug,
+    D: embedded_hal_async::delay::DelayNs,
+{
+    const READ_SIZE: usize = 1;
+
+    /// Reads data starting at the given address using the configured read mode
+    async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        self.read_into(offset, bytes).await
+    }
+
+    /// Returns the capacity of the configured chip variant
+    fn capacity(&self) -> usize {
+        self.variant().capacity() as usize
+    }
+}
+
+#[cfg(feature = "async")]
+impl<B, W, H, D> embedded_storage_async::nor_flash::NorFlash for AsyncFlash<B, W, H, D>
+where
+    B: embedded_hal_async::spi::SpiDevice<u8>,
+    W: ControlPin,
+    H: ControlPin,
+    W::Error: Debug,
+    H::Error: Debug,
+    D: embedded_hal_async::delay::DelayNs,
+{
+    const WRITE_SIZE: usize = 1;
+
+    const ERASE_SIZE: usize = SECTOR_SIZE as usize;
+
+    /// Erases the range from start (inclusive) to end (exclusive), sleeping between the status polls
+    async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        self.erase_range(from, to).await
+    }
+
+    /// Writes the given data at any address, sleeping between the status polls (s. [AsyncFlash::write])
+    async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        AsyncFlash::write(self, offset, bytes).await
+    }
+}
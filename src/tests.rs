@@ -1,6 +1,13 @@
-use crate::device::{CommandError, Flash, Memory, Status};
-use crate::mocks::{BusError, MockPin, MockSPIBus, PinError};
-use embedded_hal::spi::Operation;
+use crate::bus::{OwnedBus, OwnedBusError};
+use crate::chip::{ChipId, DeviceId, Variant};
+use crate::device::{BusyLine, CommandError, Flash, LockState, Memory, NoPin, ReadMode, Status, Timeouts};
+use crate::erase::EraseStep;
+use crate::mocks::{BusError, MockDelay, MockInputPin, MockPin, MockRawBus, MockSPIBus, PinError};
+use crate::storage::SectorStorage;
+use embedded_hal::spi;
+use embedded_hal::spi::{Operation, SpiDevice};
+use embedded_storage::nor_flash::{self, NorFlashError, NorFlashErrorKind};
+use embedded_storage::{ReadStorage, Storage};
 
 #[test]
 fn test_device_read_status_success() {
@@ -198,6 +205,278 @@ fn test_device_erase_sector_non_blocking() {
     flash.erase_sector(0x1000).unwrap();
 }
 
+#[test]
+fn test_device_erase_block_32k_address_error() {
+    let error = MockedPeripherals::default().into_flash().erase_block_32k(0x100000).unwrap_err();
+    assert!(matches!(error, CommandError::InvalidAddress))
+}
+
+#[test]
+fn test_device_erase_block_32k_alignment_error() {
+    let error = MockedPeripherals::default().into_flash().erase_block_32k(0x9000).unwrap_err();
+    assert!(matches!(error, CommandError::UnalignedAddress))
+}
+
+#[test]
+fn test_device_erase_block_32k_hold_error() {
+    let error = MockedPeripherals::hold_error().into_flash().erase_block_32k(0x0).unwrap_err();
+    assert!(matches!(error, CommandError::HoldPinError(PinError::Error1)))
+}
+
+#[test]
+fn test_device_erase_block_32k_busy() {
+    let error = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0001])
+        .into_flash()
+        .erase_block_32k(0x0)
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::Busy))
+}
+
+#[test]
+fn test_device_erase_block_32k_transfer_error() {
+    let error = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0010])
+        .spi_transfer_error()
+        .into_flash()
+        .erase_block_32k(0x0)
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::TransferError(BusError::Error1)))
+}
+
+#[test]
+fn test_device_erase_block_32k_blocking() {
+    MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0010])
+        .expect_single_write(&[0x52, 0x01, 0x80, 0x00])
+        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .into_flash()
+        .erase_block_32k(0x18000)
+        .unwrap();
+}
+
+#[test]
+fn test_device_erase_block_32k_non_blocking() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0010])
+        .expect_single_write(&[0x52, 0x00, 0x80, 0x00])
+        .into_flash();
+
+    flash.set_non_blocking();
+    flash.erase_block_32k(0x8000).unwrap();
+}
+
+#[test]
+fn test_device_erase_block_64k_address_error() {
+    let error = MockedPeripherals::default().into_flash().erase_block_64k(0x100000).unwrap_err();
+    assert!(matches!(error, CommandError::InvalidAddress))
+}
+
+#[test]
+fn test_device_erase_block_64k_alignment_error() {
+    let error = MockedPeripherals::default().into_flash().erase_block_64k(0x8000).unwrap_err();
+    assert!(matches!(error, CommandError::UnalignedAddress))
+}
+
+#[test]
+fn test_device_erase_block_64k_unsupported_error() {
+    let mut flash = MockedPeripherals::default().into_flash();
+    flash.set_variant(Variant::SST25VF010A);
+
+    let error = flash.erase_block_64k(0x0).unwrap_err();
+    assert!(matches!(error, CommandError::UnsupportedCommand))
+}
+
+#[test]
+fn test_device_erase_block_64k_wp_pin_error() {
+    let error = MockedPeripherals::wp_error().into_flash().erase_block_64k(0x0).unwrap_err();
+    assert!(matches!(
+        error,
+        CommandError::WriteProtectionPinError(PinError::Error1)
+    ))
+}
+
+#[test]
+fn test_device_erase_block_64k_busy() {
+    let error = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0001])
+        .into_flash()
+        .erase_block_64k(0x0)
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::Busy))
+}
+
+#[test]
+fn test_device_erase_block_64k_blocking() {
+    MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0010])
+        .expect_single_write(&[0xd8, 0x0f, 0x00, 0x00])
+        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
+        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .into_flash()
+        .erase_block_64k(0xf0000)
+        .unwrap();
+}
+
+#[test]
+fn test_device_erase_block_64k_non_blocking() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0010])
+        .expect_single_write(&[0xd8, 0x01, 0x00, 0x00])
+        .into_flash();
+
+    flash.set_non_blocking();
+    flash.erase_block_64k(0x10000).unwrap();
+}
+
+#[test]
+fn test_device_plan_erase_full() {
+    let plan: Vec<_> = MockedPeripherals::default()
+        .into_flash()
+        .plan_erase(0x0, 0x100000)
+        .unwrap()
+        .collect();
+    assert_eq!(vec![EraseStep::Full], plan);
+}
+
+#[test]
+fn test_device_plan_erase_mixed() {
+    let plan: Vec<_> = MockedPeripherals::default()
+        .into_flash()
+        .plan_erase(0x5000, 0x3a000)
+        .unwrap()
+        .collect();
+
+    assert_eq!(
+        vec![
+            EraseStep::Sector(0x5000),
+            EraseStep::Sector(0x6000),
+            EraseStep::Sector(0x7000),
+            EraseStep::Block32K(0x8000),
+            EraseStep::Block64K(0x10000),
+            EraseStep::Block64K(0x20000),
+            EraseStep::Block32K(0x30000),
+            EraseStep::Sector(0x38000),
+            EraseStep::Sector(0x39000),
+        ],
+        plan
+    );
+}
+
+#[test]
+fn test_device_plan_erase_without_64k_blocks() {
+    let mut flash = MockedPeripherals::default().into_flash();
+    flash.set_variant(Variant::SST25VF010A);
+
+    let plan: Vec<_> = flash.plan_erase(0x0, 0x11000).unwrap().collect();
+    assert_eq!(
+        vec![
+            EraseStep::Block32K(0x0),
+            EraseStep::Block32K(0x8000),
+            EraseStep::Sector(0x10000)
+        ],
+        plan
+    );
+
+    let plan: Vec<_> = flash.plan_erase(0x0, 0x20000).unwrap().collect();
+    assert_eq!(vec![EraseStep::Full], plan);
+}
+
+#[test]
+fn test_device_plan_erase_empty() {
+    let mut plan = MockedPeripherals::default().into_flash().plan_erase(0x8000, 0x8000).unwrap();
+    assert_eq!(None, plan.next());
+}
+
+#[test]
+fn test_device_plan_erase_alignment_error() {
+    let flash = MockedPeripherals::default().into_flash();
+
+    assert!(matches!(
+        flash.plan_erase(0x1001, 0x2000).unwrap_err(),
+        CommandError::UnalignedAddress
+    ));
+    assert!(matches!(
+        flash.plan_erase(0x1000, 0x2800).unwrap_err(),
+        CommandError::UnalignedAddress
+    ));
+}
+
+#[test]
+fn test_device_plan_erase_address_error() {
+    let flash = MockedPeripherals::default().into_flash();
+
+    assert!(matches!(
+        flash.plan_erase(0x0, 0x101000).unwrap_err(),
+        CommandError::InvalidAddress
+    ));
+    assert!(matches!(
+        flash.plan_erase(0x2000, 0x1000).unwrap_err(),
+        CommandError::InvalidAddress
+    ));
+}
+
+#[test]
+fn test_device_erase_range_blocking() {
+    MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0010])
+        .expect_single_write(&[0x20, 0x00, 0x70, 0x00])
+        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0010])
+        .expect_single_write(&[0x52, 0x00, 0x80, 0x00])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .into_flash()
+        .erase_range(0x7000, 0x10000)
+        .unwrap();
+}
+
+#[test]
+fn test_device_erase_range_non_blocking() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0010])
+        .expect_single_write(&[0xd8, 0x01, 0x00, 0x00])
+        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0010])
+        .expect_single_write(&[0x20, 0x02, 0x00, 0x00])
+        .into_flash();
+
+    flash.set_non_blocking();
+    flash.erase_range(0x10000, 0x21000).unwrap();
+}
+
+#[test]
+fn test_device_erase_range_alignment_error() {
+    let error = MockedPeripherals::default().into_flash().erase_range(0x0, 0x800).unwrap_err();
+    assert!(matches!(error, CommandError::UnalignedAddress))
+}
+
 #[test]
 fn test_device_erase_full_hold_error() {
     let error = MockedPeripherals::hold_error().into_flash().erase_full().unwrap_err();
@@ -315,243 +594,2013 @@ fn test_device_byte_program_non_blocking() {
         .expect_single_write(&[0b0000_0010, 0b0000_1101, 0b1011_1011, 0b1010_0000, 0x96])
         .into_flash();
 
-    flash.set_non_blocking();
-    flash.byte_program(0xdbba0, 0x96).unwrap();
+    flash.set_non_blocking();
+    flash.byte_program(0xdbba0, 0x96).unwrap();
+}
+
+#[test]
+fn test_device_byte_program_blocking() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0010])
+        .expect_single_write(&[0b0000_0010, 0b0000_0000, 0b1001_1100, 0b0100_0000, 0x66])
+        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
+        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .into_flash();
+
+    flash.set_blocking();
+    flash.byte_program(0x9c40, 0x66).unwrap();
+}
+
+#[test]
+fn test_device_write_status_hold_error() {
+    let error = MockedPeripherals::hold_error()
+        .into_flash()
+        .write_status(Status::default())
+        .unwrap_err();
+    assert!(matches!(error, CommandError::HoldPinError(PinError::Error1)))
+}
+
+#[test]
+fn test_device_write_status_wp_pin_error() {
+    let error = MockedPeripherals::wp_error()
+        .into_flash()
+        .write_status(Status::default())
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        CommandError::WriteProtectionPinError(PinError::Error1)
+    ))
+}
+
+#[test]
+fn test_device_write_status_transfer_error() {
+    let error = MockedPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0101_0000])
+        .spi_transfer_error()
+        .into_flash()
+        .write_status(Status::default())
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::TransferError(BusError::Error1)))
+}
+
+#[test]
+fn test_device_write_status_success() {
+    let status = Status {
+        block0_protected: true,
+        block3_protected: true,
+        ..Default::default()
+    };
+
+    MockedPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0101_0000])
+        .expect_single_write(&[0b0000_0001, 0b0010_0100])
+        .expect_status_request(&[0x0, 0b0010_0101]) // Still busy
+        .expect_status_request(&[0x0, 0b0010_0100])
+        .expect_status_request(&[0x0, 0b0010_0100])
+        .into_flash()
+        .write_status(status)
+        .unwrap();
+}
+
+#[test]
+fn test_device_write_status_write_enable_sequence() {
+    let status = Status {
+        block1_protected: true,
+        bits_read_only: true,
+        ..Default::default()
+    };
+
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_single_write(&[0b0000_0001, 0b1000_1000])
+        .expect_status_request(&[0x0, 0b1000_1000])
+        .expect_status_request(&[0x0, 0b1000_1000])
+        .into_flash();
+
+    flash.set_variant(Variant::SST25PF040C);
+    flash.write_status(status).unwrap();
+}
+
+#[test]
+fn test_device_write_status_reserved_bits_ignored() {
+    let status = Status {
+        block0_protected: true,
+        block2_protected: true,
+        block3_protected: true,
+        ..Default::default()
+    };
+
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0101_0000])
+        .expect_single_write(&[0b0000_0001, 0b0011_0100])
+        .expect_status_request(&[0x0, 0b0000_0100])
+        .expect_status_request(&[0x0, 0b0000_0100])
+        .into_flash();
+
+    flash.set_variant(Variant::SST25VF010A);
+    flash.write_status(status).unwrap();
+}
+
+#[test]
+fn test_device_write_status_not_written() {
+    let status = Status {
+        block0_protected: false,
+        ..Default::default()
+    };
+
+    let error = MockedPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0101_0000])
+        .expect_single_write(&[0b0000_0001, 0b0000_0000])
+        .expect_status_request(&[0x0, 0b0011_1100])
+        .expect_status_request(&[0x0, 0b0011_1100])
+        .into_flash()
+        .write_status(status)
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::StatusWriteFailed))
+}
+
+#[test]
+fn test_device_write_status_locked() {
+    let error = MockedPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0101_0000])
+        .expect_single_write(&[0b0000_0001, 0b0000_0000])
+        .expect_status_request(&[0x0, 0b1011_1100])
+        .expect_status_request(&[0x0, 0b1011_1100])
+        .into_flash()
+        .write_status(Status::default())
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::StatusLocked))
+}
+
+#[test]
+fn test_device_write_status_bpl_without_wp() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_wp_high()
+        .expect_single_write(&[0b0101_0000])
+        .expect_single_write(&[0b0000_0001, 0b0000_0000])
+        .expect_status_request(&[0x0, 0b1011_1100])
+        .expect_status_request(&[0x0, 0b1011_1100])
+        .into_flash();
+
+    flash.set_write_protection(false).unwrap();
+    let error = flash.write_status(Status::default()).unwrap_err();
+
+    assert!(matches!(error, CommandError::StatusWriteFailed))
+}
+
+#[test]
+fn test_device_set_write_protection_pin_error() {
+    let mut peripherals = MockedPeripherals::default().mock_configure();
+    peripherals
+        .pin_wp
+        .expect_set_high()
+        .times(1)
+        .return_const(Err(PinError::Error1));
+    let mut flash = peripherals.into_flash();
+
+    let error = flash.set_write_protection(false).unwrap_err();
+
+    assert!(matches!(
+        error,
+        CommandError::WriteProtectionPinError(PinError::Error1)
+    ));
+    assert!(flash.write_protection());
+}
+
+#[test]
+fn test_device_lock_status_success() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_status_request(&[0x0, 0b0000_1100])
+        .expect_single_write(&[0b0101_0000])
+        .expect_single_write(&[0b0000_0001, 0b1000_1100])
+        .expect_status_request(&[0x0, 0b1000_1100])
+        .expect_status_request(&[0x0, 0b1000_1100])
+        .expect_wp_low()
+        .into_flash();
+
+    flash.lock_status().unwrap();
+    assert!(flash.write_protection());
+}
+
+#[test]
+fn test_device_unlock_status_success() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_wp_high()
+        .expect_status_request(&[0x0, 0b1000_1100])
+        .expect_single_write(&[0b0101_0000])
+        .expect_single_write(&[0b0000_0001, 0b0000_1100])
+        .expect_status_request(&[0x0, 0b0000_1100])
+        .expect_status_request(&[0x0, 0b0000_1100])
+        .into_flash();
+
+    flash.unlock_status().unwrap();
+    assert!(!flash.write_protection());
+}
+
+#[test]
+fn test_device_lock_state() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_status_request(&[0x0, 0b0000_1100])
+        .expect_status_request(&[0x0, 0b1000_1100])
+        .expect_wp_high()
+        .expect_status_request(&[0x0, 0b1000_1100])
+        .into_flash();
+
+    assert_eq!(LockState::Unlocked, flash.lock_state().unwrap());
+    assert_eq!(LockState::Locked, flash.lock_state().unwrap());
+
+    flash.set_write_protection(false).unwrap();
+    assert_eq!(LockState::Armed, flash.lock_state().unwrap());
+}
+
+#[test]
+fn test_device_read_hold_error() {
+    let error = MockedPeripherals::hold_error().into_flash().read::<1>(0x0).unwrap_err();
+    assert!(matches!(error, CommandError::HoldPinError(PinError::Error1)))
+}
+
+#[test]
+fn test_device_read_wp_pin_error() {
+    let error = MockedPeripherals::wp_error().into_flash().read::<1>(0x0).unwrap_err();
+    assert!(matches!(
+        error,
+        CommandError::WriteProtectionPinError(PinError::Error1)
+    ))
+}
+
+#[test]
+fn test_device_read_address_error() {
+    let error = MockedPeripherals::default().into_flash().read::<1>(16777217).unwrap_err();
+    assert!(matches!(error, CommandError::InvalidAddress))
+}
+
+#[test]
+fn test_device_read_transfer_error_command() {
+    let error = MockedPeripherals::default()
+        .mock_configure()
+        .spi_transfer_error()
+        .into_flash()
+        .read::<1>(0x0)
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::TransferError(BusError::Error1)))
+}
+
+#[test]
+fn test_device_read_single_transaction() {
+    let mut peripherals = MockedPeripherals::default().mock_configure();
+    peripherals.bus.expect_transaction().times(1).returning(move |operations| {
+        match operations {
+            [Operation::Write(command), Operation::Read(buffer)] => {
+                assert_eq!(&[0b0000_0011, 0x0, 0x1, 0x2], command);
+                assert_eq!(3, buffer.len());
+                buffer.copy_from_slice(&[0x5, 0x6, 0x7]);
+            }
+            _ => panic!("Unexpected operations: {operations:?}"),
+        }
+
+        Ok(())
+    });
+
+    assert_eq!(
+        [0x5, 0x6, 0x7],
+        peripherals.into_flash().read::<3>(0x102).unwrap()
+    );
+}
+
+#[test]
+fn test_device_read_success() {
+    let result = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(
+            &[0b0000_0011, 0b0000_0110, 0b0001_1010, 0b1000_0000],
+            &[0x47, 0x20],
+        )
+        .into_flash()
+        .read::<2>(0x61A80)
+        .unwrap();
+
+    assert_eq!([0x47, 0x20], result)
+}
+
+#[test]
+fn test_device_read_address_beyond_capacity() {
+    let error = MockedPeripherals::default().into_flash().read::<2>(0xfffff).unwrap_err();
+    assert!(matches!(error, CommandError::InvalidAddress))
+}
+
+#[test]
+fn test_device_read_address_at_capacity() {
+    let error = MockedPeripherals::default().into_flash().read::<1>(0x100000).unwrap_err();
+    assert!(matches!(error, CommandError::InvalidAddress))
+}
+
+#[test]
+fn test_device_read_last_address() {
+    let result = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b0000_0011, 0x0f, 0xff, 0xfe], &[0x47, 0x20])
+        .into_flash()
+        .read::<2>(0xffffe)
+        .unwrap();
+
+    assert_eq!([0x47, 0x20], result)
+}
+
+#[test]
+fn test_device_read_variant_capacity() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b0000_0011, 0x7f, 0xff, 0xff], &[0x47])
+        .into_flash();
+
+    flash.set_variant(Variant::SST25VF064C);
+    assert_eq!([0x47], flash.read::<1>(0x7fffff).unwrap());
+
+    flash.set_variant(Variant::SST25WF512);
+    let error = flash.read::<1>(0x10000).unwrap_err();
+    assert!(matches!(error, CommandError::InvalidAddress))
+}
+
+#[test]
+fn test_device_read_high_speed() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(
+            &[0b0000_1011, 0b0000_0110, 0b0001_1010, 0b1000_0000, 0x0],
+            &[0x47, 0x20, 0x11],
+        )
+        .into_flash();
+
+    assert_eq!(ReadMode::Normal, flash.read_mode());
+    flash.set_read_mode(ReadMode::HighSpeed);
+    assert_eq!(ReadMode::HighSpeed, flash.read_mode());
+
+    assert_eq!([0x47, 0x20, 0x11], flash.read::<3>(0x61A80).unwrap());
+}
+
+#[test]
+fn test_device_read_high_speed_unsupported() {
+    let mut flash = MockedPeripherals::default().into_flash();
+    flash.set_variant(Variant::SST25VF010A);
+    flash.set_read_mode(ReadMode::HighSpeed);
+
+    let error = flash.read::<1>(0x0).unwrap_err();
+    assert!(matches!(error, CommandError::UnsupportedCommand))
+}
+
+#[test]
+fn test_device_read_into_success() {
+    let mut buffer = [0x0; 3];
+
+    MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b0000_0011, 0x00, 0x10, 0x00], &[0x47, 0x20, 0x11])
+        .into_flash()
+        .read_into(0x1000, &mut buffer)
+        .unwrap();
+
+    assert_eq!([0x47, 0x20, 0x11], buffer)
+}
+
+#[test]
+fn test_device_read_into_address_error() {
+    let mut buffer = [0x0; 4096];
+    let error = MockedPeripherals::default()
+        .into_flash()
+        .read_into(0xff001, &mut buffer)
+        .unwrap_err();
+    assert!(matches!(error, CommandError::InvalidAddress))
+}
+
+#[test]
+fn test_device_read_into_transfer_error() {
+    let mut buffer = [0x0; 3];
+    let error = MockedPeripherals::default()
+        .mock_configure()
+        .spi_transfer_error()
+        .into_flash()
+        .read_into(0x0, &mut buffer)
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::TransferError(BusError::Error1)))
+}
+
+#[test]
+fn test_device_read_with_chunks() {
+    let mut data = Vec::new();
+
+    MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b0000_0011, 0x00, 0x10, 0x00], &[0x1; 64])
+        .expect_command_response(&[0b0000_0011, 0x00, 0x10, 0x40], &[0x2; 64])
+        .expect_command_response(&[0b0000_0011, 0x00, 0x10, 0x80], &[0x3; 22])
+        .into_flash()
+        .read_with(0x1000, 150, |chunk| data.push(chunk.to_vec()))
+        .unwrap();
+
+    assert_eq!(vec![vec![0x1; 64], vec![0x2; 64], vec![0x3; 22]], data);
+}
+
+#[test]
+fn test_device_read_with_address_error() {
+    let error = MockedPeripherals::default()
+        .into_flash()
+        .read_with(0xfff00, 257, |_| panic!("Unexpected chunk"))
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::InvalidAddress))
+}
+
+#[test]
+fn test_device_read_with_transfer_error() {
+    let error = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b0000_0011, 0x00, 0x00, 0x00], &[0x1; 64])
+        .spi_transfer_error()
+        .into_flash()
+        .read_with(0x0, 100, |_| {})
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::TransferError(BusError::Error1)))
+}
+
+#[test]
+fn test_device_is_erased_chunks() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_read_with(&[0b0000_0011, 0x0, 0x10, 0x0], |buffer| {
+            assert_eq!(64, buffer.len());
+            buffer.fill(0xff);
+        })
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x40], &[0xff, 0xff])
+        .into_flash();
+
+    assert!(flash.is_erased(0x1000..0x1042).unwrap());
+}
+
+#[test]
+fn test_device_is_erased_false() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x0], &[0xff, 0x7f])
+        .into_flash();
+
+    assert!(!flash.is_erased(0x1000..0x1002).unwrap());
+}
+
+#[test]
+fn test_device_find_first_non_blank() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_read_with(&[0b0000_0011, 0x0, 0x10, 0x0], |buffer| buffer.fill(0xff))
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x40], &[0xff, 0x0, 0xff])
+        .into_flash();
+
+    assert_eq!(Some(0x1041), flash.find_first_non_blank(0x1000..0x1043).unwrap());
+}
+
+#[test]
+fn test_device_find_first_non_blank_empty_range() {
+    let mut flash = MockedPeripherals::default().into_flash();
+
+    assert_eq!(None, flash.find_first_non_blank(0x1000..0x1000).unwrap());
+    assert!(flash.is_erased(0x1000..0x1000).unwrap());
+}
+
+#[test]
+fn test_device_find_first_non_blank_out_of_range() {
+    let error = MockedPeripherals::default()
+        .into_flash()
+        .find_first_non_blank(0xff000..0x101000)
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::InvalidAddress));
+}
+
+#[test]
+fn test_device_is_erased_transfer_error() {
+    let error = MockedPeripherals::default()
+        .mock_configure()
+        .spi_transfer_error()
+        .into_flash()
+        .is_erased(0x1000..0x2000)
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::TransferError(BusError::Error1)));
+}
+
+#[test]
+fn test_device_aai_program_hold_error() {
+    let error = MockedPeripherals::hold_error()
+        .into_flash()
+        .aai_program(0x0, &[0x0, 0x0])
+        .unwrap_err();
+    assert!(matches!(error, CommandError::HoldPinError(PinError::Error1)))
+}
+
+#[test]
+fn test_device_aai_program_wp_pin_error() {
+    let error = MockedPeripherals::wp_error()
+        .into_flash()
+        .aai_program(0x0, &[0x0, 0x0])
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        CommandError::WriteProtectionPinError(PinError::Error1)
+    ))
+}
+
+#[test]
+fn test_device_aai_program_address_error() {
+    let error = MockedPeripherals::default()
+        .into_flash()
+        .aai_program(16777217, &[0x0, 0x0])
+        .unwrap_err();
+    assert!(matches!(error, CommandError::InvalidAddress))
+}
+
+#[test]
+fn test_device_aai_program_range_error() {
+    let error = MockedPeripherals::default()
+        .into_flash()
+        .aai_program(0xffffe, &[0x0, 0x0, 0x0, 0x0])
+        .unwrap_err();
+    assert!(matches!(error, CommandError::InvalidAddress))
+}
+
+#[test]
+fn test_device_aai_program_unsupported_error() {
+    let mut flash = MockedPeripherals::default().into_flash();
+    flash.set_variant(Variant::SST25VF064C);
+
+    let error = flash.aai_program(0x0, &[0x0, 0x0]).unwrap_err();
+    assert!(matches!(error, CommandError::UnsupportedCommand))
+}
+
+#[test]
+fn test_device_aai_program_buffer_too_small_error() {
+    let error = MockedPeripherals::default().into_flash().aai_program(0x0, &[0x0]).unwrap_err();
+    assert!(matches!(error, CommandError::BufferTooSmall))
+}
+
+#[test]
+fn test_device_aai_program_buffer_uneven_error() {
+    let error = MockedPeripherals::default()
+        .into_flash()
+        .aai_program(0x0, &[0x0, 0x0, 0x0])
+        .unwrap_err();
+    assert!(matches!(error, CommandError::BufferUneven))
+}
+
+#[test]
+fn test_device_aai_program_busy_error() {
+    let error = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0001])
+        .into_flash()
+        .aai_program(0x0, &[0x0, 0x0])
+        .unwrap_err();
+    assert!(matches!(error, CommandError::Busy))
+}
+
+#[test]
+fn test_device_aai_program_transfer_error() {
+    let error = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .spi_transfer_error()
+        .into_flash()
+        .aai_program(0x0, &[0x0, 0x0])
+        .unwrap_err();
+    assert!(matches!(error, CommandError::TransferError(BusError::Error1)))
+}
+
+#[test]
+fn test_device_aai_program_two_bytes() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b1010_1101, 0b0000_0111, 0b1010_0001, 0b0010_0000, 0x96, 0x64])
+        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
+        .expect_status_request(&[0x0, 0b0000_0000]) // Not busy anymore
+        .expect_write_disable_command()
+        .into_flash();
+
+    flash.set_non_blocking();
+    flash.aai_program(0x7A120, &[0x96, 0x64]).unwrap();
+}
+
+#[test]
+fn test_device_aai_program_six_bytes() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b1010_1101, 0b0000_0111, 0b1010_0001, 0b0010_0000, 0x96, 0x64])
+        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b1010_1101, 0x44, 0x55])
+        .expect_status_request(&[0x0, 0b0000_0000]) // Not busy anymore
+        .expect_single_write(&[0b1010_1101, 0x66, 0x77])
+        .expect_status_request(&[0x0, 0b0000_0000]) // not busy
+        .expect_write_disable_command()
+        .into_flash();
+
+    flash.set_non_blocking();
+    flash.aai_program(0x7A120, &[0x96, 0x64, 0x44, 0x55, 0x66, 0x77]).unwrap();
+}
+
+#[test]
+fn test_device_read_jedec_id_hold_error() {
+    let error = MockedPeripherals::hold_error().into_flash().read_jedec_id().unwrap_err();
+    assert!(matches!(error, CommandError::HoldPinError(PinError::Error1)))
+}
+
+#[test]
+fn test_device_read_jedec_id_transfer_error() {
+    let error = MockedPeripherals::default()
+        .mock_configure()
+        .spi_transfer_error()
+        .into_flash()
+        .read_jedec_id()
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::TransferError(BusError::Error1)))
+}
+
+#[test]
+fn test_device_read_jedec_id_success() {
+    let id = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b1001_1111], &[0xbf, 0x25, 0x41])
+        .into_flash()
+        .read_jedec_id()
+        .unwrap();
+
+    assert_eq!(0xbf, id.manufacturer);
+    assert_eq!(0x25, id.memory_type);
+    assert_eq!(0x41, id.capacity);
+    assert_eq!(Some(Variant::SST25VF016B), id.variant());
+}
+
+#[test]
+fn test_device_read_id_wp_pin_error() {
+    let error = MockedPeripherals::wp_error().into_flash().read_id().unwrap_err();
+    assert!(matches!(
+        error,
+        CommandError::WriteProtectionPinError(PinError::Error1)
+    ))
+}
+
+#[test]
+fn test_device_read_id_transfer_error() {
+    let error = MockedPeripherals::default()
+        .mock_configure()
+        .spi_transfer_error()
+        .into_flash()
+        .read_id()
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::TransferError(BusError::Error1)))
+}
+
+#[test]
+fn test_device_read_id_success() {
+    let id = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b1001_0000, 0x0, 0x0, 0x0], &[0xbf, 0x49])
+        .into_flash()
+        .read_id()
+        .unwrap();
+
+    assert_eq!(0xbf, id.manufacturer);
+    assert_eq!(0x49, id.device);
+    assert_eq!(Some(Variant::SST25VF010A), id.variant());
+}
+
+#[test]
+fn test_device_probe_jedec_id() {
+    let peripherals = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b1001_1111], &[0xbf, 0x25, 0x4a]);
+
+    let flash = Flash::probe(peripherals.bus, peripherals.pin_wp, peripherals.pin_hold).unwrap();
+    assert_eq!(Variant::SST25VF032B, flash.variant());
+}
+
+#[test]
+fn test_device_probe_transfer_error() {
+    let peripherals = MockedPeripherals::default().mock_configure().spi_transfer_error();
+
+    let result = Flash::probe(peripherals.bus, peripherals.pin_wp, peripherals.pin_hold);
+    assert!(matches!(
+        result,
+        Err(CommandError::TransferError(BusError::Error1))
+    ))
+}
+
+#[test]
+fn test_device_detect_legacy_id_fallback() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b1001_1111], &[0xff, 0xff, 0xff])
+        .expect_command_response(&[0b1001_0000, 0x0, 0x0, 0x0], &[0xbf, 0x49])
+        .into_flash();
+
+    assert_eq!(Variant::SST25VF010A, flash.detect().unwrap());
+    assert_eq!(Variant::SST25VF010A, flash.variant());
+}
+
+#[test]
+fn test_device_detect_no_chip_high() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b1001_1111], &[0xff, 0xff, 0xff])
+        .expect_command_response(&[0b1001_0000, 0x0, 0x0, 0x0], &[0xff, 0xff])
+        .into_flash();
+
+    let error = flash.detect().unwrap_err();
+    assert!(matches!(error, CommandError::NoChip));
+    assert_eq!(Variant::SST25VF080B, flash.variant());
+}
+
+#[test]
+fn test_device_detect_no_chip_low() {
+    let error = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b1001_1111], &[0x00, 0x00, 0x00])
+        .expect_command_response(&[0b1001_0000, 0x0, 0x0, 0x0], &[0x00, 0x00])
+        .into_flash()
+        .detect()
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::NoChip))
+}
+
+#[test]
+fn test_device_detect_unknown_chip() {
+    let error = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b1001_1111], &[0xef, 0x40, 0x18])
+        .expect_command_response(&[0b1001_0000, 0x0, 0x0, 0x0], &[0xef, 0x17])
+        .into_flash()
+        .detect()
+        .unwrap_err();
+
+    let expected = ChipId {
+        manufacturer: 0xef,
+        memory_type: 0x40,
+        capacity: 0x18,
+    };
+    assert!(matches!(error, CommandError::UnknownChip(id) if id == expected))
+}
+
+#[test]
+fn test_chip_id_variant() {
+    let id = ChipId {
+        manufacturer: 0xbf,
+        memory_type: 0x25,
+        capacity: 0x8e,
+    };
+    assert_eq!(Some(Variant::SST25VF080B), id.variant());
+
+    let id = ChipId {
+        manufacturer: 0x62,
+        memory_type: 0x06,
+        capacity: 0x13,
+    };
+    assert_eq!(Some(Variant::SST25PF040C), id.variant());
+
+    let id = ChipId {
+        manufacturer: 0xbf,
+        memory_type: 0x26,
+        capacity: 0x8e,
+    };
+    assert_eq!(None, id.variant());
+
+    for variant in Variant::ALL {
+        if let Some(id) = variant.jedec_id() {
+            assert_eq!(Some(variant), id.variant());
+        }
+    }
+}
+
+#[test]
+fn test_chip_id_blank() {
+    assert!(ChipId::from_bytes([0xff, 0xff, 0xff]).is_blank());
+    assert!(ChipId::from_bytes([0x00, 0x00, 0x00]).is_blank());
+    assert!(!ChipId::from_bytes([0xff, 0x00, 0xff]).is_blank());
+    assert!(!ChipId::from_bytes([0xbf, 0x25, 0x8e]).is_blank());
+
+    assert!(DeviceId::from_bytes([0xff, 0xff]).is_blank());
+    assert!(!DeviceId::from_bytes([0xbf, 0x8e]).is_blank());
+}
+
+#[test]
+fn test_device_id_variant() {
+    let id = DeviceId {
+        manufacturer: 0xbf,
+        device: 0x4b,
+    };
+    assert_eq!(Some(Variant::SST25VF064C), id.variant());
+
+    let id = DeviceId {
+        manufacturer: 0xff,
+        device: 0xff,
+    };
+    assert_eq!(None, id.variant());
+
+    for variant in Variant::ALL {
+        assert_eq!(Some(variant), variant.device_id().variant());
+    }
+}
+
+#[test]
+fn test_variant_catalog() {
+    assert_eq!(
+        Variant::SST25VF080B,
+        Flash::new(MockSPIBus::new(), MockPin::new(), MockPin::new()).variant()
+    );
+
+    assert_eq!(131072, Variant::SST25VF010A.capacity());
+    assert_eq!(1048576, Variant::SST25VF080B.capacity());
+    assert_eq!(8388608, Variant::SST25VF064C.capacity());
+    assert_eq!(65536, Variant::SST25WF512.capacity());
+
+    for variant in Variant::ALL {
+        assert_eq!(4096, variant.sector_size());
+        assert_eq!(0, variant.capacity() % 65536);
+    }
+
+    assert!(!Variant::SST25VF010A.commands().block_erase_64k);
+    assert!(!Variant::SST25VF010A.commands().jedec_id);
+    assert!(Variant::SST25VF080B.commands().aai_word_program);
+    assert!(!Variant::SST25VF064C.commands().aai_word_program);
+    assert!(!Variant::SST25PF040C.commands().enable_write_status);
+}
+
+#[test]
+fn test_variant_protected_range() {
+    assert_eq!(0x100000..0x100000, Variant::SST25VF080B.protected_range(0b0000));
+    assert_eq!(0xf0000..0x100000, Variant::SST25VF080B.protected_range(0b0001));
+    assert_eq!(0xe0000..0x100000, Variant::SST25VF080B.protected_range(0b0010));
+    assert_eq!(0xc0000..0x100000, Variant::SST25VF080B.protected_range(0b0011));
+    assert_eq!(0x80000..0x100000, Variant::SST25VF080B.protected_range(0b0100));
+    assert_eq!(0x0..0x100000, Variant::SST25VF080B.protected_range(0b0101));
+    assert_eq!(0x0..0x100000, Variant::SST25VF080B.protected_range(0b1111));
+
+    assert_eq!(0x18000..0x20000, Variant::SST25VF010A.protected_range(0b01));
+    assert_eq!(0x0..0x20000, Variant::SST25VF010A.protected_range(0b11));
+    assert_eq!(0x7f0000..0x800000, Variant::SST25VF064C.protected_range(0b0001));
+    assert_eq!(0x0..0x800000, Variant::SST25VF064C.protected_range(0b1000));
+
+    for variant in Variant::ALL {
+        for bits in 0..=variant.protection_levels() + 1 {
+            let range = variant.protected_range(bits);
+            assert_eq!(variant.capacity(), range.end);
+            assert_eq!(Some(bits), variant.protection_bits(range));
+        }
+    }
+}
+
+#[test]
+fn test_variant_protection_bits_unsupported() {
+    assert_eq!(Some(0), Variant::SST25VF080B.protection_bits(0x0..0x0));
+    assert_eq!(None, Variant::SST25VF080B.protection_bits(0x0..0x10000));
+    assert_eq!(None, Variant::SST25VF080B.protection_bits(0xf0000..0xfffff));
+    assert_eq!(None, Variant::SST25VF080B.protection_bits(0xf8000..0x100000));
+    assert_eq!(None, Variant::SST25VF080B.protection_bits(0x0..0x200000));
+}
+
+#[test]
+fn test_variant_status_mask() {
+    assert_eq!(0b1000_1100, Variant::SST25VF010A.status_mask());
+    assert_eq!(0b1001_1100, Variant::SST25VF040B.status_mask());
+    assert_eq!(0b1011_1100, Variant::SST25VF080B.status_mask());
+    assert_eq!(0b1011_1100, Variant::SST25VF064C.status_mask());
+}
+
+#[test]
+fn test_device_protected_range() {
+    let range = MockedPeripherals::default()
+        .mock_configure()
+        .expect_status_request(&[0x0, 0b1000_1110])
+        .into_flash()
+        .protected_range()
+        .unwrap();
+
+    assert_eq!(0xc0000..0x100000, range);
+}
+
+#[test]
+fn test_device_protect_range_success() {
+    MockedPeripherals::default()
+        .mock_configure()
+        .expect_status_request(&[0x0, 0b1011_1100])
+        .expect_single_write(&[0b0101_0000])
+        .expect_single_write(&[0b0000_0001, 0b1000_1000])
+        .expect_status_request(&[0x0, 0b1000_1000])
+        .expect_status_request(&[0x0, 0b1000_1000])
+        .into_flash()
+        .protect_range(0xe0000..0x100000)
+        .unwrap();
+}
+
+#[test]
+fn test_device_protect_range_unprotect() {
+    MockedPeripherals::default()
+        .mock_configure()
+        .expect_status_request(&[0x0, 0b0011_1100])
+        .expect_single_write(&[0b0101_0000])
+        .expect_single_write(&[0b0000_0001, 0b0000_0000])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .into_flash()
+        .protect_range(0x0..0x0)
+        .unwrap();
+}
+
+#[test]
+fn test_device_protect_range_unsupported() {
+    let error = MockedPeripherals::default()
+        .into_flash()
+        .protect_range(0x0..0x80000)
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::UnsupportedRange));
+}
+
+#[test]
+fn test_device_aai_program_busy_line() {
+    let mut peripherals = MockedPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0111_0000])
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b1010_1101, 0b0000_0111, 0b1010_0001, 0b0010_0000, 0x96, 0x64])
+        .expect_single_write(&[0b1010_1101, 0x44, 0x55])
+        .expect_write_disable_command()
+        .expect_single_write(&[0b1000_0000]);
+
+    let mut results = vec![Ok(true), Ok(true), Ok(false), Ok(false)].into_iter();
+    peripherals
+        .bus
+        .expect_is_busy()
+        .times(4)
+        .returning(move || results.next().unwrap());
+
+    peripherals
+        .into_flash()
+        .aai_program_with_busy_line(0x7A120, &[0x96, 0x64, 0x44, 0x55])
+        .unwrap();
+}
+
+#[test]
+fn test_device_aai_program_busy_line_error() {
+    let mut peripherals = MockedPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0111_0000])
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b1010_1101, 0x0, 0x0, 0x0, 0x96, 0x64])
+        .expect_write_disable_command()
+        .expect_single_write(&[0b1000_0000]);
+    peripherals.bus.expect_is_busy().times(1).return_const(Err(BusError::Error1));
+
+    let error = peripherals
+        .into_flash()
+        .aai_program_with_busy_line(0x0, &[0x96, 0x64])
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::BusyLineError(BusError::Error1)))
+}
+
+#[test]
+fn test_device_aai_program_busy_line_buffer_error() {
+    let error = MockedPeripherals::default()
+        .into_flash()
+        .aai_program_with_busy_line(0x0, &[0x96, 0x64, 0x1])
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::BufferUneven))
+}
+
+#[test]
+fn test_device_aai_program_busy_line_fallback() {
+    let mut flash = MockedPeripherals::default().into_flash();
+    flash.set_variant(Variant::SST25VF010A);
+
+    let error = flash.aai_program_with_busy_line(0x2, &[0x96, 0x64]).unwrap_err();
+    assert!(matches!(error, CommandError::UnsupportedCommand))
+}
+
+#[test]
+fn test_nor_flash_read() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x01], &[0x1, 0x2, 0x3])
+        .into_flash();
+
+    let mut buffer = [0x0; 3];
+    nor_flash::ReadNorFlash::read(&mut flash, 0x1001, &mut buffer).unwrap();
+    assert_eq!([0x1, 0x2, 0x3], buffer);
+}
+
+#[test]
+fn test_nor_flash_read_out_of_bounds() {
+    let mut flash = MockedPeripherals::default().into_flash();
+
+    let mut buffer = [0x0; 3];
+    let error = nor_flash::ReadNorFlash::read(&mut flash, 0xffffe, &mut buffer).unwrap_err();
+    assert_eq!(NorFlashErrorKind::OutOfBounds, error.kind());
+}
+
+#[test]
+fn test_nor_flash_capacity() {
+    let mut flash = MockedPeripherals::default().into_flash();
+    assert_eq!(1048576, nor_flash::ReadNorFlash::capacity(&flash));
+
+    flash.set_variant(Variant::SST25VF032B);
+    assert_eq!(4194304, nor_flash::ReadNorFlash::capacity(&flash));
+}
+
+#[test]
+fn test_nor_flash_erase_waits_in_non_blocking_mode() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0010_0000, 0x0, 0x10, 0x0])
+        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0010_0000, 0x0, 0x20, 0x0])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        // Non-blocking mode restored afterward
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0010_0000, 0x0, 0x30, 0x0])
+        .into_flash();
+    flash.set_non_blocking();
+
+    nor_flash::NorFlash::erase(&mut flash, 0x1000, 0x3000).unwrap();
+    flash.erase_sector(0x3000).unwrap();
+}
+
+#[test]
+fn test_nor_flash_erase_not_aligned() {
+    let mut flash = MockedPeripherals::default().into_flash();
+
+    let error = nor_flash::NorFlash::erase(&mut flash, 0x1000, 0x1800).unwrap_err();
+    assert_eq!(NorFlashErrorKind::NotAligned, error.kind());
+}
+
+#[test]
+fn test_nor_flash_write_odd_address() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x01, 0x1])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b1010_1101, 0x0, 0x10, 0x02, 0x2, 0x3])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_disable_command()
+        .into_flash();
+    flash.set_non_blocking();
+
+    nor_flash::NorFlash::write(&mut flash, 0x1001, &[0x1, 0x2, 0x3]).unwrap();
+}
+
+#[test]
+fn test_device_write_head_bulk_tail() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x01, 0x1])
+        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x06, 0x6])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b1010_1101, 0x0, 0x10, 0x02, 0x2, 0x3])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b1010_1101, 0x4, 0x5])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_disable_command()
+        .into_flash();
+
+    flash.write(0x1001, &[0x1, 0x2, 0x3, 0x4, 0x5, 0x6]).unwrap();
+}
+
+#[test]
+fn test_device_write_single_byte() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x00, 0x1])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_disable_command()
+        .into_flash();
+
+    flash.write(0x1000, &[0x1]).unwrap();
+}
+
+#[test]
+fn test_device_write_empty() {
+    MockedPeripherals::default().into_flash().write(0x100000, &[]).unwrap();
+}
+
+#[test]
+fn test_device_write_out_of_range() {
+    let error = MockedPeripherals::default()
+        .into_flash()
+        .write(0xffffe, &[0x1, 0x2, 0x3])
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::InvalidAddress))
+}
+
+#[test]
+fn test_nor_flash_write_without_aai_support() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x00, 0x1])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x01, 0x2])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_disable_command()
+        .into_flash();
+    flash.set_variant(Variant::SST25VF064C);
+
+    nor_flash::NorFlash::write(&mut flash, 0x1000, &[0x1, 0x2]).unwrap();
+}
+
+#[test]
+fn test_device_write_without_aai_support() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x00, 0x1])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x01, 0x2])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_disable_command()
+        .into_flash();
+    flash.set_variant(Variant::SST25VF064C);
+
+    flash.write(0x1000, &[0x1, 0x2]).unwrap();
+}
+
+#[test]
+fn test_device_write_disables_on_error() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b1010_1101, 0x0, 0x10, 0x00, 0x1, 0x2])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .spi_transfer_error()
+        .expect_write_disable_command()
+        .into_flash();
+
+    let error = flash.write(0x1000, &[0x1, 0x2, 0x3, 0x4]).unwrap_err();
+    assert!(matches!(error, CommandError::TransferError(BusError::Error1)))
+}
+
+#[test]
+fn test_device_verify_chunks() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_read_with(&[0b0000_0011, 0x0, 0x10, 0x0], |buffer| {
+            assert_eq!(64, buffer.len());
+            buffer.fill(0x5);
+        })
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x40], &[0x5, 0x5])
+        .into_flash();
+
+    flash.verify(0x1000, &[0x5; 66]).unwrap();
+}
+
+#[test]
+fn test_device_verify_mismatch() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_read_with(&[0b0000_0011, 0x0, 0x10, 0x0], |buffer| buffer.fill(0x5))
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x40], &[0x5, 0xff])
+        .into_flash();
+
+    let error = flash.verify(0x1000, &[0x5; 66]).unwrap_err();
+    assert!(matches!(error, CommandError::VerifyFailed { address: 0x1041 }));
+}
+
+#[test]
+fn test_device_verify_out_of_range() {
+    let error = MockedPeripherals::default()
+        .into_flash()
+        .verify(0xfffff, &[0x1, 0x2])
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::InvalidAddress));
+}
+
+#[test]
+fn test_device_byte_program_verified_waits_in_non_blocking_mode() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0010])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x0, 0x66])
+        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x0], &[0x66])
+        .into_flash();
+
+    flash.set_non_blocking();
+    flash.byte_program_verified(0x1000, 0x66).unwrap();
+}
+
+#[test]
+fn test_device_byte_program_verified_mismatch() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0010])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x0, 0x66])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x0], &[0xff])
+        .into_flash();
+
+    let error = flash.byte_program_verified(0x1000, 0x66).unwrap_err();
+    assert!(matches!(error, CommandError::VerifyFailed { address: 0x1000 }));
+}
+
+#[test]
+fn test_device_aai_program_verified_success() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b1010_1101, 0x0, 0x10, 0x0, 0x1, 0x2])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_disable_command()
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x0], &[0x1, 0x2])
+        .into_flash();
+
+    flash.aai_program_verified(0x1000, &[0x1, 0x2]).unwrap();
+}
+
+#[test]
+fn test_device_write_verified_mismatch() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x00, 0x1])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_disable_command()
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x0], &[0xff])
+        .into_flash();
+
+    let error = flash.write_verified(0x1000, &[0x1]).unwrap_err();
+    assert!(matches!(error, CommandError::VerifyFailed { address: 0x1000 }));
+}
+
+#[test]
+fn test_nor_flash_write_out_of_bounds() {
+    let mut flash = MockedPeripherals::default().into_flash();
+
+    let error = nor_flash::NorFlash::write(&mut flash, 0xfffff, &[0x1, 0x2]).unwrap_err();
+    assert_eq!(NorFlashErrorKind::OutOfBounds, error.kind());
+}
+
+#[test]
+fn test_nor_flash_error_kind() {
+    let error: CommandError<MockSPIBus, MockPin> = CommandError::TransferError(BusError::Error1);
+    assert_eq!(NorFlashErrorKind::Other, error.kind());
+}
+
+#[test]
+fn test_storage_write_without_erase() {
+    let flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x01], &[0xf0])
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x01, 0x30])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_disable_command()
+        .into_flash();
+
+    let mut buffer = [0x0; 4096];
+    SectorStorage::new(flash, &mut buffer).write(0x1001, &[0x30]).unwrap();
+}
+
+#[test]
+fn test_storage_write_unchanged() {
+    let flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x01], &[0x1, 0x2])
+        .into_flash();
+
+    let mut buffer = [0x0; 4096];
+    SectorStorage::new(flash, &mut buffer).write(0x1001, &[0x1, 0x2]).unwrap();
+}
+
+#[test]
+fn test_storage_write_erases_sector() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x01], &[0x00])
+        .expect_read_with(&[0b0000_0011, 0x0, 0x10, 0x00], |buffer| {
+            assert_eq!(4096, buffer.len());
+            buffer.fill(0xff);
+            buffer[0] = 0x12;
+            buffer[1] = 0x00;
+            buffer[4095] = 0x34;
+        })
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0010_0000, 0x0, 0x10, 0x0])
+        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x00, 0x12])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_disable_command()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x1f, 0xff, 0x34])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_disable_command()
+        .into_flash();
+    flash.set_non_blocking();
+
+    let mut buffer = [0x0; 4096];
+    SectorStorage::new(flash, &mut buffer).write(0x1001, &[0xff]).unwrap();
+}
+
+#[test]
+fn test_storage_write_across_sectors() {
+    let flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b0000_0011, 0x0, 0x0f, 0xff], &[0xff])
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x0f, 0xff, 0x1])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_disable_command()
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x00], &[0xff, 0xff])
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b1010_1101, 0x0, 0x10, 0x00, 0x2, 0x3])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_disable_command()
+        .into_flash();
+
+    let mut buffer = [0x0; 4096];
+    SectorStorage::new(flash, &mut buffer).write(0xfff, &[0x1, 0x2, 0x3]).unwrap();
+}
+
+#[test]
+fn test_storage_write_out_of_range() {
+    let mut buffer = [0x0; 4096];
+    let error = SectorStorage::new(MockedPeripherals::default().into_flash(), &mut buffer)
+        .write(0xfffff, &[0x1, 0x2])
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::InvalidAddress))
+}
+
+#[test]
+fn test_storage_read() {
+    let flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x01], &[0x1, 0x2])
+        .into_flash();
+
+    let mut buffer = [0x0; 4096];
+    let mut storage = SectorStorage::new(flash, &mut buffer);
+
+    let mut data = [0x0; 2];
+    storage.read(0x1001, &mut data).unwrap();
+    assert_eq!([0x1, 0x2], data);
+    assert_eq!(1048576, storage.capacity());
+}
+
+#[test]
+fn test_device_timeout_expired() {
+    let mut delay = MockDelay::new();
+    delay.expect_delay_us().withf(|us| *us == 10).times(2).return_const(());
+
+    let timeouts = Timeouts {
+        sector_erase_us: 20,
+        ..Default::default()
+    };
+
+    let error = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0010_0000, 0x0, 0x10, 0x0])
+        .expect_status_request(&[0x0, 0b0000_0001])
+        .expect_status_request(&[0x0, 0b0000_0001])
+        .expect_status_request(&[0x0, 0b0000_0001])
+        .into_flash()
+        .with_timeouts(delay, timeouts)
+        .erase_sector(0x1000)
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::Timeout))
+}
+
+#[test]
+fn test_device_timeout_not_expired() {
+    let mut delay = MockDelay::new();
+    delay.expect_delay_us().withf(|us| *us == 10).times(1).return_const(());
+
+    MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x0, 0x66])
+        .expect_status_request(&[0x0, 0b0000_0001])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .into_flash()
+        .with_timeouts(delay, Timeouts::default())
+        .byte_program(0x1000, 0x66)
+        .unwrap();
+}
+
+#[test]
+fn test_device_timeout_chip_erase() {
+    let mut delay = MockDelay::new();
+    delay.expect_delay_us().withf(|us| *us == 1000).times(50).return_const(());
+
+    let mut peripherals = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_full_erase();
+
+    for _ in 0..51 {
+        peripherals = peripherals.expect_status_request(&[0x0, 0b0000_0001]);
+    }
+
+    let timeouts = Timeouts {
+        poll_interval_us: 1000,
+        ..Default::default()
+    };
+
+    let error = peripherals
+        .into_flash()
+        .with_timeouts(delay, timeouts)
+        .erase_full()
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::Timeout))
+}
+
+#[test]
+fn test_device_timeout_non_blocking() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0010_0000, 0x0, 0x10, 0x0])
+        .into_flash()
+        .with_timeouts(MockDelay::new(), Timeouts::default());
+    flash.set_non_blocking();
+
+    flash.erase_sector(0x1000).unwrap();
+}
+
+#[test]
+fn test_device_timeout_busy_line() {
+    let mut delay = MockDelay::new();
+    delay.expect_delay_us().times(1).return_const(());
+
+    let timeouts = Timeouts {
+        byte_program_us: 10,
+        poll_interval_us: 10,
+        ..Default::default()
+    };
+
+    let mut peripherals = MockedPeripherals::default()
+        .mock_configure()
+        .expect_single_write(&[0b0111_0000])
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b1010_1101, 0x0, 0x0, 0x0, 0x96, 0x64])
+        .expect_write_disable_command()
+        .expect_single_write(&[0b1000_0000]);
+    peripherals.bus.expect_is_busy().times(2).return_const(Ok(true));
+
+    let error = peripherals
+        .into_flash()
+        .with_timeouts(delay, timeouts)
+        .aai_program_with_busy_line(0x0, &[0x96, 0x64])
+        .unwrap_err();
+
+    assert!(matches!(error, CommandError::Timeout))
+}
+
+#[test]
+fn test_device_with_timeouts_keeps_configuration() {
+    let mut flash = MockedPeripherals::default().into_flash();
+    flash.set_variant(Variant::SST25VF016B);
+    flash.set_read_mode(ReadMode::HighSpeed);
+    assert_eq!(None, flash.timeouts());
+
+    let flash = flash.with_timeouts(MockDelay::new(), Timeouts::default());
+    assert_eq!(Variant::SST25VF016B, flash.variant());
+    assert_eq!(ReadMode::HighSpeed, flash.read_mode());
+    assert_eq!(Some(Timeouts::default()), flash.timeouts());
+}
+
+#[test]
+fn test_timeouts_for_variant() {
+    assert_eq!(Timeouts::default(), Timeouts::for_variant(Variant::SST25VF080B));
+
+    let expected = [
+        (Variant::SST25VF010A, 25_000, 25_000, 100_000, 20),
+        (Variant::SST25VF020B, 25_000, 25_000, 50_000, 10),
+        (Variant::SST25VF040B, 25_000, 25_000, 50_000, 10),
+        (Variant::SST25VF080B, 25_000, 25_000, 50_000, 10),
+        (Variant::SST25VF016B, 25_000, 25_000, 50_000, 10),
+        (Variant::SST25VF032B, 25_000, 25_000, 50_000, 10),
+        (Variant::SST25VF064C, 25_000, 25_000, 50_000, 2_500),
+        (Variant::SST25PF040C, 25_000, 25_000, 50_000, 2_500),
+        (Variant::SST25WF512, 150_000, 150_000, 250_000, 60),
+        (Variant::SST25WF010, 150_000, 150_000, 250_000, 60),
+        (Variant::SST25WF020, 150_000, 150_000, 250_000, 60),
+        (Variant::SST25WF040, 150_000, 150_000, 250_000, 60),
+        (Variant::SST25WF080, 150_000, 150_000, 250_000, 60),
+    ];
+
+    for (variant, sector_erase_us, block_erase_us, chip_erase_us, byte_program_us) in expected {
+        let timeouts = Timeouts::for_variant(variant);
+
+        assert_eq!(sector_erase_us, timeouts.sector_erase_us, "{variant:?}");
+        assert_eq!(block_erase_us, timeouts.block_erase_us, "{variant:?}");
+        assert_eq!(chip_erase_us, timeouts.chip_erase_us, "{variant:?}");
+        assert_eq!(byte_program_us, timeouts.byte_program_us, "{variant:?}");
+        assert_eq!(10, timeouts.poll_interval_us);
+    }
+
+    assert_eq!(Variant::ALL.len(), expected.len());
+}
+
+#[test]
+fn test_pending_erase_sector() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0010_0000, 0x0, 0x10, 0x0])
+        .expect_status_request(&[0x0, 0b0000_0001])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .into_flash();
+
+    let mut pending = flash.start_erase_sector(0x1000).unwrap();
+    assert!(matches!(pending.poll(), Err(nb::Error::WouldBlock)));
+    assert!(!pending.is_completed());
+
+    // Chip is not accessed anymore after completion
+    assert!(pending.poll().is_ok());
+    assert!(pending.poll().is_ok());
+    assert!(pending.is_completed());
+}
+
+#[test]
+fn test_pending_restores_blocking_mode() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x0, 0x66])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x1, 0x67])
+        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .into_flash();
+
+    flash.start_byte_program(0x1000, 0x66).unwrap().wait().unwrap();
+    flash.byte_program(0x1001, 0x67).unwrap();
+}
+
+#[test]
+fn test_pending_erase_block() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0101_0010, 0x0, 0x80, 0x0])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b1101_1000, 0x01, 0x0, 0x0])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .into_flash();
+
+    flash.start_erase_block_32k(0x8000).unwrap().wait().unwrap();
+    flash.start_erase_block_64k(0x10000).unwrap().wait().unwrap();
+}
+
+#[test]
+fn test_pending_erase_full() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_full_erase()
+        .expect_status_request(&[0x0, 0b0000_0001])
+        .into_flash();
+
+    let mut pending = flash.start_erase_full().unwrap();
+    assert!(matches!(pending.poll(), Err(nb::Error::WouldBlock)));
+}
+
+#[test]
+fn test_pending_start_busy() {
+    let error = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0001])
+        .into_flash()
+        .start_erase_sector(0x1000)
+        .err()
+        .unwrap();
+
+    assert!(matches!(error, CommandError::Busy))
+}
+
+#[test]
+fn test_pending_poll_transfer_error() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_write_enable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b0000_0010, 0x0, 0x10, 0x0, 0x66])
+        .spi_transfer_error()
+        .into_flash();
+
+    let mut pending = flash.start_byte_program(0x1000, 0x66).unwrap();
+    let error = pending.poll().unwrap_err();
+
+    assert!(matches!(
+        error,
+        nb::Error::Other(CommandError::TransferError(BusError::Error1))
+    ));
+}
+
+#[test]
+fn test_aai_writer_success() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_enable_command()
+        .expect_single_write(&[0b1010_1101, 0x0, 0x10, 0x0, 0x1, 0x2])
+        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_single_write(&[0b1010_1101, 0x3, 0x4])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_disable_command()
+        .into_flash();
+
+    let data = [0x1, 0x2, 0x3, 0x4];
+    let mut writer = flash.aai_writer(0x1000, &data).unwrap();
+
+    assert!(matches!(writer.poll(&mut flash), Err(nb::Error::WouldBlock)));
+    assert_eq!(2, writer.written());
+    assert_eq!(2, writer.remaining());
+    assert!(writer.is_active());
+
+    assert!(matches!(writer.poll(&mut flash), Err(nb::Error::WouldBlock)));
+    assert_eq!(2, writer.written());
+
+    assert!(matches!(writer.poll(&mut flash), Err(nb::Error::WouldBlock)));
+    assert_eq!(4, writer.written());
+    assert!(!writer.is_completed());
+
+    assert!(writer.poll(&mut flash).is_ok());
+    assert!(writer.is_completed());
+    assert!(!writer.is_active());
+
+    // No further commands after completion
+    assert!(writer.poll(&mut flash).is_ok());
+}
+
+#[test]
+fn test_aai_writer_pause_resume() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_enable_command()
+        .expect_single_write(&[0b1010_1101, 0x0, 0x10, 0x0, 0x1, 0x2])
+        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_disable_command()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_enable_command()
+        .expect_single_write(&[0b1010_1101, 0x0, 0x10, 0x2, 0x3, 0x4])
+        .into_flash();
+
+    let data = [0x1, 0x2, 0x3, 0x4];
+    let mut writer = flash.aai_writer(0x1000, &data).unwrap();
+    assert!(matches!(writer.poll(&mut flash), Err(nb::Error::WouldBlock)));
+
+    assert!(matches!(writer.pause(&mut flash), Err(nb::Error::WouldBlock)));
+    assert!(writer.pause(&mut flash).is_ok());
+    assert!(!writer.is_active());
+    assert_eq!(0x1002, writer.next_address());
+
+    // Already paused
+    assert!(writer.pause(&mut flash).is_ok());
+
+    assert!(matches!(writer.poll(&mut flash), Err(nb::Error::WouldBlock)));
+    assert_eq!(4, writer.written());
+}
+
+#[test]
+fn test_aai_writer_rejects_commands_while_active() {
+    let mut flash = MockedPeripherals::default()
+        .mock_configure()
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_enable_command()
+        .expect_single_write(&[0b1010_1101, 0x0, 0x10, 0x0, 0x1, 0x2])
+        .expect_status_request(&[0x0, 0b0100_0000])
+        .expect_status_request(&[0x0, 0b0100_0000])
+        .expect_write_disable_command()
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x0], &[0x1, 0x2])
+        .into_flash();
+
+    let data = [0x1, 0x2, 0x3, 0x4];
+    let mut writer = flash.aai_writer(0x1000, &data).unwrap();
+    assert!(matches!(writer.poll(&mut flash), Err(nb::Error::WouldBlock)));
+
+    assert!(matches!(flash.read::<2>(0x1000).unwrap_err(), CommandError::Busy));
+    assert!(matches!(
+        flash.erase_sector(0x2000).unwrap_err(),
+        CommandError::Busy
+    ));
+    assert!(matches!(
+        flash.byte_program(0x2000, 0x1).unwrap_err(),
+        CommandError::Busy
+    ));
+    assert!(flash.read_status().unwrap().aai_programming_mode);
+
+    writer.pause(&mut flash).unwrap();
+    assert_eq!([0x1, 0x2], flash.read::<2>(0x1000).unwrap());
 }
 
 #[test]
-fn test_device_byte_program_blocking() {
+fn test_aai_writer_error_recovery() {
     let mut flash = MockedPeripherals::default()
         .mock_configure()
+        .expect_status_request(&[0x0, 0b0000_0000])
         .expect_write_enable_command()
-        .expect_status_request(&[0x0, 0b0000_0010])
-        .expect_single_write(&[0b0000_0010, 0b0000_0000, 0b1001_1100, 0b0100_0000, 0x66])
-        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
-        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
+        .expect_single_write(&[0b1010_1101, 0x0, 0x10, 0x0, 0x1, 0x2])
+        .expect_status_request(&[0x0, 0b0000_0000])
+        .spi_transfer_error()
+        .expect_write_disable_command()
         .expect_status_request(&[0x0, 0b0000_0000])
+        .expect_write_enable_command()
+        .expect_single_write(&[0b1010_1101, 0x0, 0x10, 0x2, 0x3, 0x4])
         .into_flash();
 
-    flash.set_blocking();
-    flash.byte_program(0x9c40, 0x66).unwrap();
-}
+    let data = [0x1, 0x2, 0x3, 0x4];
+    let mut writer = flash.aai_writer(0x1000, &data).unwrap();
+    assert!(matches!(writer.poll(&mut flash), Err(nb::Error::WouldBlock)));
 
-#[test]
-fn test_device_write_status_hold_error() {
-    let error = MockedPeripherals::hold_error()
-        .into_flash()
-        .write_status(Status::default())
-        .unwrap_err();
-    assert!(matches!(error, CommandError::HoldPinError(PinError::Error1)))
+    let error = writer.poll(&mut flash).unwrap_err();
+    assert!(matches!(
+        error,
+        nb::Error::Other(CommandError::TransferError(BusError::Error1))
+    ));
+    assert!(!writer.is_active());
+    assert_eq!(2, writer.written());
+
+    // Resumed at the failed word
+    assert!(matches!(writer.poll(&mut flash), Err(nb::Error::WouldBlock)));
 }
 
 #[test]
-fn test_device_write_status_wp_pin_error() {
-    let error = MockedPeripherals::wp_error()
-        .into_flash()
-        .write_status(Status::default())
-        .unwrap_err();
+fn test_aai_writer_invalid_buffer() {
+    let flash = MockedPeripherals::default().into_flash();
+
     assert!(matches!(
-        error,
-        CommandError::WriteProtectionPinError(PinError::Error1)
-    ))
+        flash.aai_writer(0x1000, &[0x1, 0x2, 0x3]).unwrap_err(),
+        CommandError::BufferUneven
+    ));
+    assert!(matches!(
+        flash.aai_writer(0xffffe, &[0x1, 0x2, 0x3, 0x4]).unwrap_err(),
+        CommandError::InvalidAddress
+    ));
 }
 
 #[test]
-fn test_device_write_status_transfer_error() {
-    let error = MockedPeripherals::default()
+fn test_device_reader_chunks() {
+    let mut flash = MockedPeripherals::default()
         .mock_configure()
-        .expect_write_enable_command()
-        .spi_transfer_error()
-        .into_flash()
-        .write_status(Status::default())
-        .unwrap_err();
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x0], &[0x1, 0x2])
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x2], &[0x3, 0x4])
+        .expect_command_response(&[0b0000_0011, 0x0, 0x10, 0x4], &[0x5])
+        .into_flash();
 
-    assert!(matches!(error, CommandError::TransferError(BusError::Error1)))
-}
+    let mut reader = flash.reader(0x1000, 5).unwrap();
+    let mut buffer = [0x0; 2];
 
-#[test]
-fn test_device_write_status_success() {
-    let status = Status {
-        block0_protected: true,
-        block3_protected: true,
-        ..Default::default()
-    };
+    assert_eq!(2, reader.read_next(&mut flash, &mut buffer).unwrap());
+    assert_eq!([0x1, 0x2], buffer);
 
-    MockedPeripherals::default()
-        .mock_configure()
-        .expect_write_enable_command()
-        .expect_single_write(&[0x0])
-        .expect_single_write(&[0b0000_0001, 0b0010_0100])
-        .into_flash()
-        .write_status(status)
-        .unwrap();
+    assert_eq!(2, reader.read_next(&mut flash, &mut buffer).unwrap());
+    assert_eq!([0x3, 0x4], buffer);
+    assert_eq!(0x1004, reader.next_address());
+
+    assert_eq!(1, reader.read_next(&mut flash, &mut buffer).unwrap());
+    assert_eq!(0x5, buffer[0]);
+    assert!(reader.is_completed());
+
+    assert_eq!(0, reader.read_next(&mut flash, &mut buffer).unwrap());
 }
 
 #[test]
-fn test_device_read_hold_error() {
-    let error = MockedPeripherals::hold_error().into_flash().read::<1>(0x0).unwrap_err();
-    assert!(matches!(error, CommandError::HoldPinError(PinError::Error1)))
+fn test_device_reader_transfer_error() {
+    let mut flash = MockedPeripherals::default().mock_configure().spi_transfer_error().into_flash();
+
+    let mut reader = flash.reader(0x1000, 4).unwrap();
+
+    let error = reader.read_next(&mut flash, &mut [0x0; 4]).unwrap_err();
+    assert!(matches!(error, CommandError::TransferError(BusError::Error1)));
+    assert_eq!(0, reader.offset());
 }
 
 #[test]
-fn test_device_read_wp_pin_error() {
-    let error = MockedPeripherals::wp_error().into_flash().read::<1>(0x0).unwrap_err();
+fn test_device_reader_invalid_range() {
+    let flash = MockedPeripherals::default().into_flash();
+
     assert!(matches!(
-        error,
-        CommandError::WriteProtectionPinError(PinError::Error1)
-    ))
+        flash.reader(0xfffff, 2).unwrap_err(),
+        CommandError::InvalidAddress
+    ));
 }
 
 #[test]
-fn test_device_read_address_error() {
-    let error = MockedPeripherals::default().into_flash().read::<1>(16777217).unwrap_err();
-    assert!(matches!(error, CommandError::InvalidAddress))
+fn test_device_no_pins() {
+    let peripherals = MockedPeripherals::default()
+        .expect_status_request(&[0x0, 0b1000_0000])
+        .expect_status_request(&[0x0, 0b0000_0001]);
+    let mut flash = Flash::new(peripherals.bus, NoPin::default(), NoPin::default());
+
+    let error = flash.set_write_protection(true).unwrap_err();
+    assert!(matches!(error, CommandError::UnsupportedCommand));
+    flash.set_write_protection(false).unwrap();
+
+    assert!(!flash.write_protection());
+    assert_eq!(LockState::Armed, flash.lock_state().unwrap());
+    assert!(flash.read_status().unwrap().busy);
 }
 
 #[test]
-fn test_device_read_transfer_error_command() {
-    let error = MockedPeripherals::default()
-        .mock_configure()
-        .spi_transfer_error()
-        .into_flash()
-        .read::<1>(0x0)
-        .unwrap_err();
+fn test_device_no_pins_lock_status_unsupported() {
+    let peripherals = MockedPeripherals::default();
+    let mut flash = Flash::new(peripherals.bus, NoPin::default(), NoPin::default());
 
-    assert!(matches!(error, CommandError::TransferError(BusError::Error1)))
+    let error = flash.lock_status().unwrap_err();
+    assert!(matches!(error, CommandError::UnsupportedCommand));
 }
 
 #[test]
-fn test_device_read_transfer_error_data() {
-    let error = MockedPeripherals::default()
-        .mock_configure()
-        .expect_single_write(&[0b0000_0011, 0x0, 0x0, 0x0])
-        .spi_transfer_error()
-        .into_flash()
-        .read::<1>(0x0)
-        .unwrap_err();
-
-    assert!(matches!(error, CommandError::TransferError(BusError::Error1)))
+fn test_device_no_pins_write_status_failed() {
+    let peripherals = MockedPeripherals::default()
+        .expect_single_write(&[0b0101_0000])
+        .expect_single_write(&[0b0000_0001, 0b0000_0000])
+        .expect_status_request(&[0x0, 0b1011_1100])
+        .expect_status_request(&[0x0, 0b1011_1100]);
+    let mut flash = Flash::new(peripherals.bus, NoPin::default(), NoPin::default());
+
+    let error = flash.write_status(Status::default()).unwrap_err();
+    assert!(matches!(error, CommandError::StatusWriteFailed))
 }
 
 #[test]
-fn test_device_read_success() {
-    let result = MockedPeripherals::default()
-        .mock_configure()
-        .expect_transfer(
-            &[0b0000_0011, 0b0000_0110, 0b0001_1010, 0b1000_0000],
-            &[0x47, 0x20],
-        )
-        .into_flash()
-        .read::<2>(0x61A80)
-        .unwrap();
-
-    assert_eq!([0x47, 0x20], result)
+fn test_device_heterogeneous_pins_hold_error() {
+    let mut peripherals = MockedPeripherals::default();
+    peripherals
+        .pin_hold
+        .expect_set_high()
+        .times(1)
+        .return_const(Err(PinError::Error1));
+    let mut flash = Flash::new(peripherals.bus, NoPin::default(), peripherals.pin_hold);
+
+    let error = flash.read_status().unwrap_err();
+    assert!(matches!(error, CommandError::HoldPinError(PinError::Error1)));
 }
 
 #[test]
-fn test_device_aai_program_hold_error() {
-    let error = MockedPeripherals::hold_error()
-        .into_flash()
-        .aai_program(0x0, &[0x0, 0x0])
-        .unwrap_err();
-    assert!(matches!(error, CommandError::HoldPinError(PinError::Error1)))
-}
+fn test_device_heterogeneous_pins_wp_error() {
+    let mut peripherals = MockedPeripherals::default();
+    peripherals.pin_wp.expect_set_low().times(1).return_const(Err(PinError::Error1));
+    let mut flash = Flash::new(peripherals.bus, peripherals.pin_wp, NoPin::default());
 
-#[test]
-fn test_device_aai_program_wp_pin_error() {
-    let error = MockedPeripherals::wp_error()
-        .into_flash()
-        .aai_program(0x0, &[0x0, 0x0])
-        .unwrap_err();
+    let error = flash.read_status().unwrap_err();
     assert!(matches!(
         error,
         CommandError::WriteProtectionPinError(PinError::Error1)
-    ))
-}
-
-#[test]
-fn test_device_aai_program_address_error() {
-    let error = MockedPeripherals::default()
-        .into_flash()
-        .aai_program(16777217, &[0x0, 0x0])
-        .unwrap_err();
-    assert!(matches!(error, CommandError::InvalidAddress))
+    ));
 }
 
 #[test]
-fn test_device_aai_program_buffer_too_small_error() {
-    let error = MockedPeripherals::default().into_flash().aai_program(0x0, &[0x0]).unwrap_err();
-    assert!(matches!(error, CommandError::BufferTooSmall))
-}
-
-#[test]
-fn test_device_aai_program_buffer_uneven_error() {
-    let error = MockedPeripherals::default()
-        .into_flash()
-        .aai_program(0x0, &[0x0, 0x0, 0x0])
-        .unwrap_err();
-    assert!(matches!(error, CommandError::BufferUneven))
+fn test_command_error_debug() {
+    type Error = CommandError<MockSPIBus, MockPin>;
+
+    assert_eq!(
+        "TransferError(Error1)",
+        format!("{:?}", Error::TransferError(BusError::Error1))
+    );
+    assert_eq!(
+        "HoldPinError(Error1)",
+        format!("{:?}", Error::HoldPinError(PinError::Error1))
+    );
+    assert_eq!(
+        "WriteProtectionPinError(Error1)",
+        format!("{:?}", Error::WriteProtectionPinError(PinError::Error1))
+    );
 }
 
 #[test]
-fn test_device_aai_program_busy_error() {
-    let error = MockedPeripherals::default()
+fn test_device_release_success() {
+    let flash = MockedPeripherals::default()
         .mock_configure()
-        .expect_write_enable_command()
-        .expect_status_request(&[0x0, 0b0000_0001])
-        .into_flash()
-        .aai_program(0x0, &[0x0, 0x0])
-        .unwrap_err();
-    assert!(matches!(error, CommandError::Busy))
-}
+        .expect_status_request(&[0x0, 0b0100_0001]) // Still busy
+        .expect_status_request(&[0x0, 0b0100_0000])
+        .expect_write_disable_command()
+        .into_flash();
 
-#[test]
-fn test_device_aai_program_transfer_error() {
-    let error = MockedPeripherals::default()
-        .mock_configure()
-        .expect_write_enable_command()
-        .expect_status_request(&[0x0, 0b0000_0000])
-        .spi_transfer_error()
-        .into_flash()
-        .aai_program(0x0, &[0x0, 0x0])
-        .unwrap_err();
-    assert!(matches!(error, CommandError::TransferError(BusError::Error1)))
+    let (_bus, _pin_wp, _pin_hold) = flash.release().map_err(|(_, error)| error).unwrap();
 }
 
 #[test]
-fn test_device_aai_program_two_bytes() {
-    let mut flash = MockedPeripherals::default()
-        .mock_configure()
-        .expect_write_enable_command()
-        .expect_status_request(&[0x0, 0b0000_0000])
-        .expect_single_write(&[0b1010_1101, 0b0000_0111, 0b1010_0001, 0b0010_0000, 0x96, 0x64])
-        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
-        .expect_status_request(&[0x0, 0b0000_0000]) // Not busy anymore
-        .expect_write_disable_command()
-        .into_flash();
+fn test_device_release_transfer_error() {
+    let flash = MockedPeripherals::default().mock_configure().spi_transfer_error().into_flash();
 
-    flash.set_non_blocking();
-    flash.aai_program(0x7A120, &[0x96, 0x64]).unwrap();
+    let (flash, error) = flash.release().err().unwrap();
+    assert!(matches!(error, CommandError::TransferError(BusError::Error1)));
+
+    // Freeing without accessing the chip
+    let (_bus, _pin_wp, _pin_hold) = flash.free();
 }
 
 #[test]
-fn test_device_aai_program_six_bytes() {
-    let mut flash = MockedPeripherals::default()
-        .mock_configure()
-        .expect_write_enable_command()
-        .expect_status_request(&[0x0, 0b0000_0000])
-        .expect_single_write(&[0b1010_1101, 0b0000_0111, 0b1010_0001, 0b0010_0000, 0x96, 0x64])
-        .expect_status_request(&[0x0, 0b0000_0001]) // Still busy
-        .expect_status_request(&[0x0, 0b0000_0000])
-        .expect_single_write(&[0b1010_1101, 0x44, 0x55])
-        .expect_status_request(&[0x0, 0b0000_0000]) // Not busy anymore
-        .expect_single_write(&[0b1010_1101, 0x66, 0x77])
-        .expect_status_request(&[0x0, 0b0000_0000]) // not busy
-        .expect_write_disable_command()
-        .into_flash();
+fn test_status_block_protection() {
+    let mut status = Status::from_register(0b1001_0100);
+    assert_eq!(0b0101, status.block_protection());
 
-    flash.set_non_blocking();
-    flash.aai_program(0x7A120, &[0x96, 0x64, 0x44, 0x55, 0x66, 0x77]).unwrap();
+    status.set_block_protection(0b1010);
+    assert!(!status.block0_protected);
+    assert!(status.block1_protected);
+    assert!(!status.block2_protected);
+    assert!(status.block3_protected);
+    assert!(status.bits_read_only);
 }
 
 #[test]
@@ -605,6 +2654,90 @@ fn test_status_to_registers() {
     assert_eq!(0b0001_1000, status.to_registers());
 }
 
+#[test]
+fn test_owned_bus_transaction() {
+    let mut spi = MockRawBus::new();
+    spi.expect_write()
+        .withf(|words| words == [0b0000_0101])
+        .times(1)
+        .return_const(Ok(()));
+    spi.expect_read().times(1).returning(|words| {
+        words[0] = 0x1c;
+        Ok(())
+    });
+    spi.expect_flush().times(1).return_const(Ok(()));
+
+    let mut pin_cs = MockPin::new();
+    pin_cs.expect_set_low().times(1).return_const(Ok(()));
+    pin_cs.expect_set_high().times(1).return_const(Ok(()));
+
+    let mut bus = OwnedBus::new(spi, pin_cs, MockInputPin::new());
+    let mut response = [0x0];
+    bus.transaction(&mut [Operation::Write(&[0b0000_0101]), Operation::Read(&mut response)])
+        .unwrap();
+
+    assert_eq!([0x1c], response);
+}
+
+#[test]
+fn test_owned_bus_transaction_error_releases_cs() {
+    let mut spi = MockRawBus::new();
+    spi.expect_write().times(1).return_const(Err(BusError::Error1));
+    spi.expect_flush().times(1).return_const(Ok(()));
+
+    let mut pin_cs = MockPin::new();
+    pin_cs.expect_set_low().times(1).return_const(Ok(()));
+    pin_cs.expect_set_high().times(1).return_const(Ok(()));
+
+    let mut bus = OwnedBus::new(spi, pin_cs, MockInputPin::new());
+    let error = bus.transaction(&mut [Operation::Write(&[0b0000_0101])]).unwrap_err();
+
+    assert!(matches!(error, OwnedBusError::Bus(BusError::Error1)));
+}
+
+#[test]
+fn test_owned_bus_transaction_cs_error() {
+    let mut pin_cs = MockPin::new();
+    pin_cs.expect_set_low().times(1).return_const(Err(PinError::Error1));
+
+    let mut bus = OwnedBus::new(MockRawBus::new(), pin_cs, MockInputPin::new());
+    let error = bus.transaction(&mut [Operation::Write(&[0b0000_0101])]).unwrap_err();
+
+    assert!(matches!(error, OwnedBusError::ChipSelect(PinError::Error1)));
+    assert_eq!(spi::ErrorKind::ChipSelectFault, spi::Error::kind(&error));
+}
+
+#[test]
+fn test_owned_bus_is_busy_asserts_cs() {
+    let mut pin_cs = MockPin::new();
+    pin_cs.expect_set_low().times(2).return_const(Ok(()));
+    pin_cs.expect_set_high().times(2).return_const(Ok(()));
+
+    let mut pin_miso = MockInputPin::new();
+    let mut results = vec![Ok(true), Ok(false)].into_iter();
+    pin_miso.expect_is_low().times(2).returning(move || results.next().unwrap());
+
+    let mut bus = OwnedBus::new(MockRawBus::new(), pin_cs, pin_miso);
+
+    assert!(bus.is_busy().unwrap());
+    assert!(!bus.is_busy().unwrap());
+}
+
+#[test]
+fn test_owned_bus_is_busy_miso_error() {
+    let mut pin_cs = MockPin::new();
+    pin_cs.expect_set_low().times(1).return_const(Ok(()));
+    pin_cs.expect_set_high().times(1).return_const(Ok(()));
+
+    let mut pin_miso = MockInputPin::new();
+    pin_miso.expect_is_low().times(1).return_const(Err(PinError::Error1));
+
+    let mut bus = OwnedBus::new(MockRawBus::new(), pin_cs, pin_miso);
+    let error = bus.is_busy().unwrap_err();
+
+    assert!(matches!(error, OwnedBusError::Miso(PinError::Error1)));
+}
+
 #[derive(Default)]
 struct MockedPeripherals {
     pub pin_hold: MockPin,
@@ -644,6 +2777,18 @@ impl MockedPeripherals {
         self
     }
 
+    /// Expects WP# to be asserted (driven low)
+    pub fn expect_wp_low(mut self) -> Self {
+        self.pin_wp.expect_set_low().times(1).return_const(Ok(()));
+        self
+    }
+
+    /// Expects WP# to be released (driven high)
+    pub fn expect_wp_high(mut self) -> Self {
+        self.pin_wp.expect_set_high().times(1).return_const(Ok(()));
+        self
+    }
+
     /// Mocks the one-time configuration logic
     pub fn mock_configure(mut self) -> Self {
         self.pin_hold.expect_set_high().times(1).return_const(Ok(()));
@@ -693,11 +2838,6 @@ impl MockedPeripherals {
         self.expect_single_write(&[0b0110_0000])
     }
 
-    /// Expects a generic command
-    pub fn expect_transfer(self, command: &'static [u8], response: &'static [u8]) -> Self {
-        self.expect_single_write(command).expect_single_read(response)
-    }
-
     /// Expects a single write operation
     pub fn expect_single_write(mut self, command: &'static [u8]) -> Self {
         self.bus.expect_transaction().times(1).returning(move |operations| {
@@ -715,14 +2855,50 @@ impl MockedPeripherals {
         self
     }
 
-    pub fn expect_single_read(mut self, response: &'static [u8]) -> Self {
+    /// Expects a command followed by reading the given response within one transaction
+    pub fn expect_command_response(mut self, command: &'static [u8], response: &'static [u8]) -> Self {
         self.bus.expect_transaction().times(1).returning(move |operations| {
-            assert_eq!(1, operations.len(), "Operations: {operations:?}");
-            match &mut operations[0] {
+            assert_eq!(2, operations.len(), "Operations: {operations:?}");
+
+            match &operations[0] {
+                Operation::Write(data) => {
+                    assert_eq!(&command, data);
+                }
+                _ => panic!("Expected first operation to be Write"),
+            }
+
+            match &mut operations[1] {
                 Operation::Read(buffer) => {
+                    assert_eq!(response.len(), buffer.len(), "Unexpected read length");
                     buffer.copy_from_slice(response);
                 }
-                _ => panic!("Expected Read operation"),
+                _ => panic!("Expected second operation to be Read"),
+            }
+
+            Ok(())
+        });
+
+        self
+    }
+
+    /// Expects a command followed by a read operation, which is handled by the given function
+    pub fn expect_read_with<F>(mut self, command: &'static [u8], f: F) -> Self
+    where
+        F: Fn(&mut [u8]) + Send + 'static,
+    {
+        self.bus.expect_transaction().times(1).returning(move |operations| {
+            assert_eq!(2, operations.len(), "Operations: {operations:?}");
+
+            match &operations[0] {
+                Operation::Write(data) => {
+                    assert_eq!(&command, data);
+                }
+                _ => panic!("Expected first operation to be Write"),
+            }
+
+            match &mut operations[1] {
+                Operation::Read(buffer) => f(buffer),
+                _ => panic!("Expected second operation to be Read"),
             }
 
             Ok(())